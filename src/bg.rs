@@ -1,25 +1,38 @@
-use std::sync::{Arc, mpsc};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 use ttf_parser::GlyphId;
-use image::RgbImage;
-use rustybuzz::Face;
 
-use super::FaceState;
+use super::{FaceState, GlyphKey, OversizeGlyphs, RenderResult, TooLarge};
 
 enum BgCmd {
-    AddFace {
-        face_data: Arc<Vec<u8>>, face: Face<'static>,
-        border_texels: f32, texels_per_em_x: f32, texels_per_em_y: f32
-    },
+    AddFace { face_index: usize, face_state: Box<FaceState> },
+    ReplaceFace { face_index: usize, face_state: Box<FaceState> },
+    RemoveFace(usize),
+    /// Drops any renders for the given face that are still queued. (This
+    /// happens as soon as the command is received, ahead of the queue.)
+    CancelRenders(usize),
     RenderGlyph {
-        face_index: usize, glyph_id: GlyphId,
-        atlas_w: u32, atlas_h: u32,
+        face_index: usize, glyph_id: GlyphId, tier: f32,
+        atlas_w: u32, atlas_h: u32, oversize: OversizeGlyphs,
     },
 }
 
+/// A glyph that the background thread has rendered.
+pub(crate) struct BgRendered {
+    pub key: GlyphKey,
+    pub result: Result<RenderResult, TooLarge>,
+    /// How long it took to render.
+    pub time: Duration,
+    /// The revision of the face that it was rendered from.
+    pub revision: u64,
+}
+
 pub(crate) struct Renderer {
     command_tx: mpsc::Sender<BgCmd>,
-    glyph_rx: mpsc::Receiver<(usize, u16, f32, f32, f32, f32, u32, u32,
-                              RgbImage)>,
+    glyph_rx: mpsc::Receiver<BgRendered>,
 }
 
 impl Renderer {
@@ -29,29 +42,60 @@ impl Renderer {
         std::thread::Builder::new()
             .name("Psilo-Text BG glyph renderer".to_string())
             .spawn(move || {
-                let mut faces = vec![];
-                while let Ok(cmd) = command_rx.recv() {
+                let mut faces = HashMap::new();
+                let mut queue = VecDeque::new();
+                loop {
+                    if queue.is_empty() {
+                        match command_rx.recv() {
+                            Ok(cmd) => queue.push_back(cmd),
+                            Err(_) => break,
+                        }
+                    }
+                    // Take everything that's waiting, so that cancelled
+                    // glyphs aren't rendered for nothing.
+                    while let Ok(cmd) = command_rx.try_recv() {
+                        if let BgCmd::CancelRenders(cancelled) = cmd {
+                            queue.retain(|x| !matches!(x, BgCmd::RenderGlyph {
+                                face_index, ..
+                            } if *face_index == cancelled));
+                        }
+                        else { queue.push_back(cmd) }
+                    }
+                    let cmd = match queue.pop_front() {
+                        Some(x) => x,
+                        None => continue,
+                    };
                     match cmd {
-                        BgCmd::AddFace { face_data, face, border_texels,
-                                         texels_per_em_x,texels_per_em_y } => {
-                            faces.push(FaceState {
-                                _face_data: face_data, face, border_texels,
-                                texels_per_em_x, texels_per_em_y,
-                            });
+                        BgCmd::AddFace { face_index, face_state }
+                        | BgCmd::ReplaceFace { face_index, face_state } => {
+                            faces.insert(face_index, *face_state);
+                        },
+                        BgCmd::RemoveFace(face_index) => {
+                            faces.remove(&face_index);
                         },
-                        BgCmd::RenderGlyph { face_index, glyph_id,
-                                             atlas_w, atlas_h } => {
-                            let face = faces.get(face_index)
+                        BgCmd::CancelRenders(_) => (),
+                        BgCmd::RenderGlyph { face_index, glyph_id, tier,
+                                             atlas_w, atlas_h, oversize } => {
+                            let face = faces.get(&face_index)
                                 .expect("Face index out of range? (This \
                                          should not happen, as our caller \
-                                         should have bounds checked for us");
-                            let res = face.render_glyph(glyph_id,
-                                                        atlas_w, atlas_h);
-                            if let Some((a,b,c,d,e,f,g)) = res {
-                                let res = (face_index, glyph_id.0,
-                                           a,b,c,d,e,f,g);
-                                if glyph_tx.send(res).is_err() { break }
-                            }
+                                         should have bounds checked for us, \
+                                         and cancelled renders for removed \
+                                         faces)");
+                            let start = Instant::now();
+                            let result = face.render_glyph(glyph_id, tier,
+                                                           atlas_w, atlas_h,
+                                                           oversize);
+                            // Always reply, even if there's nothing to draw,
+                            // so the glyph doesn't stay pending forever.
+                            let res = BgRendered {
+                                key: face.glyph_key(face_index, glyph_id.0,
+                                                    tier),
+                                result,
+                                time: start.elapsed(),
+                                revision: face.revision,
+                            };
+                            if glyph_tx.send(res).is_err() { break }
                         },
                     }
                 }
@@ -60,25 +104,39 @@ impl Renderer {
             command_tx, glyph_rx,
         }
     }
-    pub fn add_face(&self, face_data: Arc<Vec<u8>>, face: Face<'static>,
-                    border_texels: f32, texels_per_em_x: f32,
-                    texels_per_em_y: f32) {
+    pub fn add_face(&self, face_index: usize, face_state: FaceState) {
         self.command_tx
             .send(BgCmd::AddFace {
-                face_data, face, border_texels,
-                texels_per_em_x, texels_per_em_y,
+                face_index, face_state: Box::new(face_state),
             }).expect("background render thread died?");
     }
+    pub fn replace_face(&self, face_index: usize, face_state: FaceState) {
+        self.command_tx
+            .send(BgCmd::ReplaceFace {
+                face_index, face_state: Box::new(face_state),
+            }).expect("background render thread died?");
+    }
+    pub fn remove_face(&self, face_index: usize) {
+        self.command_tx
+            .send(BgCmd::RemoveFace(face_index))
+            .expect("background render thread died?");
+    }
+    /// Cancels any of a face's glyphs that haven't been rendered yet.
+    /// (Glyphs that are already rendered may still come back.)
+    pub fn cancel_renders(&self, face_index: usize) {
+        self.command_tx
+            .send(BgCmd::CancelRenders(face_index))
+            .expect("background render thread died?");
+    }
     pub fn render_glyph(&self, face_index: usize, glyph_id: GlyphId,
-                        atlas_w: u32, atlas_h: u32) {
+                        tier: f32, atlas_w: u32, atlas_h: u32,
+                        oversize: OversizeGlyphs) {
         self.command_tx
             .send(BgCmd::RenderGlyph {
-                face_index, glyph_id, atlas_w, atlas_h,
+                face_index, glyph_id, tier, atlas_w, atlas_h, oversize,
             }).expect("background render thread died?");
     }
-    pub fn next_rendered_glyph(&self)
-        -> Option<(usize, u16, f32, f32, f32, f32, u32, u32,
-                   RgbImage)> {
+    pub fn next_rendered_glyph(&self) -> Option<BgRendered> {
             self.glyph_rx.try_recv().ok()
         }
 }
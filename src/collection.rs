@@ -0,0 +1,73 @@
+//! Looking inside font data that may hold more than one face: TrueType and
+//! OpenType collections (`.ttc` and `.otc` files).
+
+use ttf_parser::{Face, Language, name_id};
+
+/// Describes one face in some font data. See
+/// [`FaceInfo::list`](#method.list).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaceInfo {
+    /// The face's index within the font data: what to pass as the `index` to
+    /// [`add_face`](struct.TextHandler.html#method.add_face) to get this
+    /// face.
+    pub index: u32,
+    /// The family name ("Noto Sans CJK JP"), or `None` if the face has none
+    /// in an encoding we can read. The typographic family name is preferred,
+    /// so all the weights of a family share one.
+    pub family: Option<String>,
+    /// The subfamily, or style, name ("Bold Italic"), or `None` if the face
+    /// has none in an encoding we can read. As with `family`, the
+    /// typographic subfamily name is preferred.
+    pub subfamily: Option<String>,
+    /// The weight class, from 1 to 1000: 400 is regular, 700 is bold. 400 if
+    /// the face has no `OS/2` table.
+    pub weight: u16,
+    /// Whether the face is marked as italic in its `OS/2` table.
+    pub italic: bool,
+    /// How many glyphs the face has.
+    pub glyph_count: u16,
+}
+
+impl FaceInfo {
+    /// Lists the faces in the given font data: every face of a collection,
+    /// or the one face of an ordinary font file. Faces that can't be parsed
+    /// are left out, so the indices may have gaps. An empty list means the
+    /// data isn't a font at all.
+    pub fn list(face_data: &[u8]) -> Vec<FaceInfo> {
+        let count = ttf_parser::fonts_in_collection(face_data).unwrap_or(1);
+        (0 .. count).filter_map(|index| FaceInfo::get(face_data, index))
+            .collect()
+    }
+    /// Describes face number `index` of the given font data. Returns `None`
+    /// if there is no such face, or it can't be parsed.
+    pub fn get(face_data: &[u8], index: u32) -> Option<FaceInfo> {
+        let face = Face::parse(face_data, index).ok()?;
+        Some(FaceInfo {
+            index,
+            family: name(&face, &[name_id::TYPOGRAPHIC_FAMILY,
+                                  name_id::FAMILY]),
+            subfamily: name(&face, &[name_id::TYPOGRAPHIC_SUBFAMILY,
+                                     name_id::SUBFAMILY]),
+            weight: face.weight().to_number(),
+            italic: face.is_italic(),
+            glyph_count: face.number_of_glyphs(),
+        })
+    }
+}
+
+/// Returns the first of the given names that the face has in an encoding we
+/// can read, preferring an English one.
+fn name(face: &Face, ids: &[u16]) -> Option<String> {
+    for &id in ids {
+        let names = || face.names().into_iter().filter(|x| x.name_id == id);
+        let english = names()
+            .filter(|x| x.language() == Language::English_UnitedStates)
+            .find_map(|x| x.to_string());
+        if let Some(name) = english.or_else(|| {
+            names().find_map(|x| x.to_string())
+        }) {
+            return Some(name)
+        }
+    }
+    None
+}
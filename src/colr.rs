@@ -0,0 +1,96 @@
+//! Just enough of the `COLR` (version 0) and `CPAL` tables to decompose a
+//! color glyph into tinted layers. The version of `ttf-parser` we're tied to
+//! doesn't know about either table, so we read them ourselves.
+
+use ttf_parser::{Face, GlyphId, Tag};
+
+const COLR: Tag = Tag::from_bytes(b"COLR");
+const CPAL: Tag = Tag::from_bytes(b"CPAL");
+
+/// Layer palette index meaning "use the text's foreground color".
+const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset .. offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset .. offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Returns true if the given glyph has `COLR` layers.
+pub(crate) fn is_color_glyph(face: &Face, glyph: GlyphId) -> bool {
+    find_base_glyph(face, glyph).is_some()
+}
+
+/// Returns `(first_layer_index, num_layers)` for the given base glyph, if it
+/// has a record in the `COLR` table.
+fn find_base_glyph(face: &Face, glyph: GlyphId) -> Option<(u16, u16)> {
+    let colr = face.raw_face().table(COLR)?;
+    let num_base_glyphs = read_u16(colr, 2)?;
+    let base_glyphs_offset = read_u32(colr, 4)? as usize;
+    // Base glyph records are sorted by glyph ID.
+    let (mut lo, mut hi) = (0usize, num_base_glyphs as usize);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let record = base_glyphs_offset + mid * 6;
+        let id = read_u16(colr, record)?;
+        match id.cmp(&glyph.0) {
+            std::cmp::Ordering::Less => lo = mid + 1,
+            std::cmp::Ordering::Greater => hi = mid,
+            std::cmp::Ordering::Equal => {
+                return Some((read_u16(colr, record + 2)?,
+                             read_u16(colr, record + 4)?))
+            },
+        }
+    }
+    None
+}
+
+/// Decomposes a color glyph into its layers, bottom-most first. Each layer is
+/// a glyph ID and an RGBA color from the given palette, or `None` if that
+/// layer should be drawn in the foreground color.
+///
+/// Returns `None` if the glyph has no `COLR` layers, or if the tables are
+/// malformed.
+pub(crate) fn color_glyph_layers(face: &Face, glyph: GlyphId, palette: u16)
+    -> Option<Vec<(u16, Option<[u8; 4]>)>> {
+    let (first_layer, num_layers) = find_base_glyph(face, glyph)?;
+    let colr = face.raw_face().table(COLR)?;
+    let layers_offset = read_u32(colr, 8)? as usize;
+    let total_layers = read_u16(colr, 12)?;
+    if first_layer as usize + num_layers as usize > total_layers as usize {
+        return None
+    }
+    let mut ret = Vec::with_capacity(num_layers as usize);
+    for n in first_layer .. first_layer + num_layers {
+        let record = layers_offset + n as usize * 4;
+        let layer_glyph = read_u16(colr, record)?;
+        let palette_index = read_u16(colr, record + 2)?;
+        let color = if palette_index == FOREGROUND_PALETTE_INDEX { None }
+        else { palette_color(face, palette, palette_index) };
+        ret.push((layer_glyph, color));
+    }
+    Some(ret)
+}
+
+/// Looks up an entry in a `CPAL` palette, returning it as RGBA. Falls back to
+/// the first palette if the requested one doesn't exist.
+fn palette_color(face: &Face, palette: u16, entry: u16) -> Option<[u8; 4]> {
+    let cpal = face.raw_face().table(CPAL)?;
+    let num_entries = read_u16(cpal, 2)?;
+    let num_palettes = read_u16(cpal, 4)?;
+    let num_records = read_u16(cpal, 6)?;
+    let records_offset = read_u32(cpal, 8)? as usize;
+    if entry >= num_entries || num_palettes == 0 { return None }
+    let palette = if palette < num_palettes { palette } else { 0 };
+    let first_record = read_u16(cpal, 12 + palette as usize * 2)?;
+    let record = first_record as usize + entry as usize;
+    if record >= num_records as usize { return None }
+    // Records are stored BGRA.
+    let bgra = cpal.get(records_offset + record * 4
+                        .. records_offset + record * 4 + 4)?;
+    Some([bgra[2], bgra[1], bgra[0], bgra[3]])
+}
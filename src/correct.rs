@@ -0,0 +1,110 @@
+//! MSDF artifact correction. `fdsm` doesn't do any, so this is our own take on
+//! the "clash detection" pass from Chlumský's `msdfgen`: find texels whose
+//! channels disagree with a neighbor's by more than the distance between them
+//! could possibly account for, and replace them with the true (single-channel)
+//! distance to the outline.
+
+use fdsm::bezier::{Point, prepared::PreparedComponent};
+
+use crate::ErrorCorrection;
+
+/// How far apart (in texels) two channels of adjacent texels can be before we
+/// call it a clash. A hair over one, since a distance can't change by more
+/// than one texel per texel.
+const EDGE_THRESHOLD: f64 = 1.001;
+
+fn median(texel: [u8; 3]) -> u8 {
+    let [a, b, c] = texel;
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// Returns true if `a` clashes with `b` in a way that `a` should be blamed
+/// for. `threshold` is in the same units as the texel values.
+fn detect_clash(a: [u8; 3], b: [u8; 3], threshold: f64) -> bool {
+    // Sort the channel pairs from biggest to smallest difference.
+    let mut pairs = [(a[0] as f64, b[0] as f64),
+                     (a[1] as f64, b[1] as f64),
+                     (a[2] as f64, b[2] as f64)];
+    pairs.sort_by(|x, y| (y.1 - y.0).abs().total_cmp(&(x.1 - x.0).abs()));
+    let [_, (a1, b1), (a2, b2)] = pairs;
+    (b1 - a1).abs() >= threshold
+        // a texel that's already been flattened can't clash
+        && !(b[0] == b[1] && b[1] == b[2])
+        // of the pair, only flag the one that's farther from an edge
+        && (a2 - 127.5).abs() >= (b2 - 127.5).abs()
+}
+
+/// Finds and fixes clashing texels in `pixels`, an MSDF (or MTSDF, if
+/// `bytes_per_texel` is 4) `width` texels wide. It was generated from `shape`
+/// with the given distance `range` (in texels), and has already had its
+/// signs corrected. `corners` are the points (in texel coordinates) where
+/// differently-colored edges meet; `EdgePriority` leaves texels next to them
+/// alone.
+pub(crate) fn correct_errors(pixels: &mut [u8],
+                             width: u32,
+                             bytes_per_texel: usize,
+                             shape: &PreparedComponent,
+                             corners: &[Point],
+                             range: f64,
+                             mode: ErrorCorrection) {
+    if mode == ErrorCorrection::Off || width == 0 { return }
+    let height = (pixels.len() / bytes_per_texel) as u32 / width;
+    let index = |x: u32, y: u32| (y * width + x) as usize * bytes_per_texel;
+    let texel = |pixels: &[u8], x: u32, y: u32| {
+        let i = index(x, y);
+        [pixels[i], pixels[i+1], pixels[i+2]]
+    };
+    let threshold = EDGE_THRESHOLD / range * 255.0;
+    let diagonal_threshold = threshold * std::f64::consts::SQRT_2;
+    let mut flagged = vec![false; (width * height) as usize];
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let a = texel(pixels, x, y);
+            let mut check = |x2: u32, y2: u32, threshold: f64| {
+                let b = texel(pixels, x2, y2);
+                if detect_clash(a, b, threshold) {
+                    flagged[(y * width + x) as usize] = true;
+                }
+                if detect_clash(b, a, threshold) {
+                    flagged[(y2 * width + x2) as usize] = true;
+                }
+            };
+            if x + 1 < width { check(x + 1, y, threshold) }
+            if y + 1 < height { check(x, y + 1, threshold) }
+            if x + 1 < width && y + 1 < height {
+                check(x + 1, y + 1, diagonal_threshold)
+            }
+            if x + 1 < width && y > 0 {
+                check(x + 1, y - 1, diagonal_threshold)
+            }
+        }
+    }
+    for y in 0 .. height {
+        for x in 0 .. width {
+            if !flagged[(y * width + x) as usize] { continue }
+            let center = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+            if mode == ErrorCorrection::EdgePriority
+            && corners.iter().any(|corner| {
+                (corner.x - center.x).abs() <= 1.0
+                    && (corner.y - center.y).abs() <= 1.0
+            }) {
+                continue
+            }
+            // The sign of the true distance depends on the winding of the
+            // nearest edge, which the fill rule may disagree with. The median
+            // has already been corrected, so go by that.
+            let old = texel(pixels, x, y);
+            let distance = shape.distance(center).value.distance().abs();
+            let distance = if median(old) > 127 { distance }
+                           else { -distance };
+            let value = ((distance / range + 0.5).clamp(0.0, 1.0) * 255.0)
+                .round() as u8;
+            // (and don't let rounding push it across the edge)
+            let value = if distance > 0.0 { value.max(128) }
+                        else { value.min(127) };
+            // (an MTSDF's alpha channel is already the true distance)
+            let i = index(x, y);
+            pixels[i .. i + 3].copy_from_slice(&[value; 3]);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Owners of font data. A `Face` borrows its bytes for as long as it lives,
+//! so whatever holds them must never move or change them.
+
+use std::{
+    path::Path,
+    sync::Arc,
+};
+use rustybuzz::Face;
+
+/// Font data for [`TextHandler`](struct.TextHandler.html) to parse faces
+/// from. Cloning it is cheap, and clones share the same bytes; the
+/// background renderer gets a clone of its own, rather than a copy.
+///
+/// Anything that can be turned into a `FontData` can be passed to
+/// [`add_face`](struct.TextHandler.html#method.add_face) and its friends:
+/// `Arc<Vec<u8>>`, `Arc<[u8]>`, `Vec<u8>`, or `&'static [u8]` (say, from
+/// `include_bytes!`). Or, if you can promise that nothing will write to a
+/// font file while it's in use, map it straight into memory with
+/// [`map_file`](#method.map_file).
+#[derive(Clone)]
+pub struct FontData(Bytes);
+
+#[derive(Clone)]
+enum Bytes {
+    Vec(Arc<Vec<u8>>),
+    Slice(Arc<[u8]>),
+    Static(&'static [u8]),
+    #[cfg(unix)]
+    Mapped(Arc<mmap::Mapping>),
+}
+
+impl FontData {
+    /// Maps a font file into memory, read-only, instead of reading it in.
+    /// Only the parts of the file that are actually used (the tables and
+    /// glyphs we look at) are ever loaded, and they're shared with any other
+    /// process that maps the same file, which is a big saving for large
+    /// CJK fonts.
+    ///
+    /// On platforms other than Unix, this reads the whole file in instead.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be changed or truncated, by this process or any
+    /// other, for as long as the `FontData` or any clone of it is alive.
+    /// Changes would show up in data that's assumed never to change, which
+    /// is undefined behavior, and if the file got shorter, touching the
+    /// missing part would crash the process. Font editors (and artists
+    /// saving over a font while your game runs) do exactly this, so only
+    /// map files that nothing will write to. Replacing a file by writing a
+    /// new one and renaming it over the old one is fine, since that leaves
+    /// the old file, and its mapping, alone.
+    pub unsafe fn map_file<P: AsRef<Path>>(path: P)
+        -> std::io::Result<FontData> {
+        #[cfg(unix)] {
+            Ok(FontData(Bytes::Mapped(Arc::new(mmap::Mapping::new(
+                path.as_ref())?))))
+        }
+        #[cfg(not(unix))] {
+            Ok(FontData::from(std::fs::read(path)?))
+        }
+    }
+    /// The font data itself.
+    pub fn bytes(&self) -> &[u8] {
+        match &self.0 {
+            Bytes::Vec(x) => x,
+            Bytes::Slice(x) => x,
+            Bytes::Static(x) => x,
+            #[cfg(unix)]
+            Bytes::Mapped(x) => x.bytes(),
+        }
+    }
+    /// Returns true if both are the very same bytes (not just equal ones).
+    pub fn ptr_eq(&self, other: &FontData) -> bool {
+        std::ptr::eq(self.bytes(), other.bytes())
+    }
+}
+
+/// A face, together with the font data it was parsed from. This is the only
+/// place that knows the face's real lifetime; everything else only gets to
+/// borrow it for as long as it borrows us.
+#[derive(Clone)]
+pub(crate) struct OwnedFace {
+    /// Borrows from `data`. Declared first, so it's dropped first.
+    face: Face<'static>,
+    data: FontData,
+}
+
+impl OwnedFace {
+    /// Parses face number `index` of the given data. Returns `None` if there
+    /// is no such face.
+    pub fn parse(data: FontData, index: u32) -> Option<OwnedFace> {
+        // SAFETY: `data` keeps its bytes where they are, unchanged, for as
+        // long as it lives, however it's moved around. We keep it alive for
+        // as long as the face, and never let the face out with a lifetime
+        // longer than a borrow of us.
+        let bytes: &'static [u8] = unsafe { &*(data.bytes() as *const [u8]) };
+        let face = Face::from_slice(bytes, index)?;
+        Some(OwnedFace { face, data })
+    }
+    pub fn face(&self) -> &Face<'_> { &self.face }
+    pub fn data(&self) -> &FontData { &self.data }
+    /// Sets a variation axis. Returns `None` if the face has no such axis.
+    pub fn set_variation(&mut self, tag: ttf_parser::Tag, value: f32)
+        -> Option<()> {
+        self.face.set_variation(tag, value)
+    }
+    /// Puts every variation axis back at its default value.
+    pub fn reset_variations(&mut self) {
+        for axis in self.face.variation_axes() {
+            self.face.set_variation(axis.tag, axis.def_value);
+        }
+    }
+}
+
+impl From<Arc<Vec<u8>>> for FontData {
+    fn from(x: Arc<Vec<u8>>) -> FontData { FontData(Bytes::Vec(x)) }
+}
+
+impl From<Arc<[u8]>> for FontData {
+    fn from(x: Arc<[u8]>) -> FontData { FontData(Bytes::Slice(x)) }
+}
+
+impl From<Vec<u8>> for FontData {
+    fn from(x: Vec<u8>) -> FontData { FontData(Bytes::Vec(Arc::new(x))) }
+}
+
+impl From<&'static [u8]> for FontData {
+    fn from(x: &'static [u8]) -> FontData { FontData(Bytes::Static(x)) }
+}
+
+impl std::fmt::Debug for FontData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match &self.0 {
+            Bytes::Vec(_) | Bytes::Slice(_) => "heap",
+            Bytes::Static(_) => "static",
+            #[cfg(unix)]
+            Bytes::Mapped(_) => "mapped",
+        };
+        write!(f, "FontData({} bytes, {})", self.bytes().len(), kind)
+    }
+}
+
+#[cfg(unix)]
+mod mmap {
+    use std::{
+        fs::File,
+        io,
+        os::unix::io::AsRawFd,
+        path::Path,
+    };
+
+    /// A read-only, private mapping of a whole file.
+    pub(super) struct Mapping {
+        ptr: *mut libc::c_void,
+        len: usize,
+    }
+
+    // The mapping is read-only, so sharing it between threads is fine.
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        pub fn new(path: &Path) -> io::Result<Mapping> {
+            let file = File::open(path)?;
+            let len = file.metadata()?.len();
+            let len = usize::try_from(len).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData,
+                               "font file too big to map")
+            })?;
+            if len == 0 {
+                // (mmap doesn't do empty mappings.)
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "font file is empty"))
+            }
+            let ptr = unsafe {
+                libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ,
+                           libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error())
+            }
+            // The mapping outlives the file descriptor.
+            Ok(Mapping { ptr, len })
+        }
+        pub fn bytes(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.ptr as *const u8,
+                                                self.len) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.ptr, self.len); }
+        }
+    }
+}
@@ -20,14 +20,23 @@
 //!
 //! - Implement [`AtlasHandler`][2].
 //! - Create a [`TextHandler`][3].
-//! - Load your font files (or files) into a `Vec<u8>`.
-//! - Add faces with [`add_face`][4].
+//! - Load your font files (or files) into a `Vec<u8>`, or, if nothing will
+//!   write to them while they're in use, map them into memory with
+//!   [`FontData::map_file`][11].
+//! - Add faces with [`add_face`][4]. If a file is a collection of faces,
+//!   [`FaceInfo::list`][12] will tell you what's in it.
 //! - Use a shaping engine (I recommend [`rustybuzz`][5]) or a layout engine
 //!   (like Pango) to determine which glyphs to draw where. (`TextHandler` is
 //!   maintaining ownership of each font `Face`, you can use [`get_face`][6] to
 //!   get a reference that you can pass to the shaping/layout engine.)
 //! - Use [`get_glyph`][7] for each glyph to render. It will tell you which
-//!   atlas to render from, and what coordinates.
+//!   atlas to render from, and what coordinates. It will also tell you
+//!   whether that atlas holds MSDFs or (for fonts with raster glyphs) plain
+//!   bitmaps, so you can pick the right shader. [`get_glyph_info`][10] will
+//!   tell you where to draw it relative to the pen, among other things.
+//! - If you're using color fonts (emoji, icons...), check
+//!   [`is_color_glyph`][8] first, and use [`get_color_glyph`][9] to get a
+//!   stack of tinted layers for glyphs that are.
 //!
 //! The details of implementing `AtlasHandler` and actually rendering the
 //! glyphs are out of the scope of this documentation, and will depend on what
@@ -39,6 +48,11 @@
 //! [5]: https://crates.io/crates/rustybuzz
 //! [6]: struct.TextHandler.html#method.get_face
 //! [7]: struct.TextHandler.html#method.get_glyph
+//! [8]: struct.TextHandler.html#method.is_color_glyph
+//! [9]: struct.TextHandler.html#method.get_color_glyph
+//! [10]: struct.TextHandler.html#method.get_glyph_info
+//! [11]: struct.FontData.html#method.map_file
+//! [12]: struct.FaceInfo.html#method.list
 //!
 //! # Background rendering
 //!
@@ -72,18 +86,16 @@
 //! they arise.
 
 use std::{
-    collections::HashMap,
-    mem::transmute,
-    sync::Arc,
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
 };
-use ttf_parser::GlyphId;
+use ttf_parser::{GlyphId, RasterImageFormat};
 use fdsm::{
     shape::Shape,
-    transform::Transform, bezier::scanline::FillRule,
+    transform::Transform,
 };
-use image::RgbImage;
-use rect_packer::Packer;
-use rustybuzz::Face;
+use image::{RgbImage, RgbaImage, imageops::FilterType};
+use rustybuzz::{Face, Variation};
 use log::warn;
 
 type Affine = nalgebra::Affine2<f64>;
@@ -91,16 +103,126 @@ type Matrix = nalgebra::Matrix3<f64>;
 
 #[cfg(feature="bg-render")]
 mod bg;
+mod collection;
+mod colr;
+mod correct;
+mod data;
+mod pack;
+mod stems;
+
+pub use collection::FaceInfo;
+pub use data::FontData;
+use data::OwnedFace;
+pub use pack::{AtlasPacker, MaxRectsPacker, Packing, ShelfPacker,
+               SkylinePacker};
+
+/// What kind of pixels an atlas holds.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum AtlasFormat {
+    /// Multichannel signed distance fields, three bytes per texel (RGB).
+    /// Render these with an MSDF shader.
+    Msdf,
+    /// Multichannel and true signed distance fields, four bytes per texel.
+    /// RGB holds an MSDF, as above, and alpha holds the ordinary
+    /// single-channel distance, which effects like soft shadows and glows
+    /// want. See [`MsdfConfig::mtsdf`](struct.MsdfConfig.html#structfield.mtsdf).
+    Mtsdf,
+    /// Plain color bitmaps, from fonts with raster glyphs (such as `sbix` or
+    /// `CBDT` emoji fonts). Four bytes per texel (non-premultiplied RGBA).
+    /// Render these by sampling them directly.
+    Bitmap,
+}
+
+impl AtlasFormat {
+    /// The number of bytes in each texel of this format.
+    pub fn bytes_per_texel(&self) -> usize {
+        match self {
+            AtlasFormat::Msdf => 3,
+            AtlasFormat::Mtsdf | AtlasFormat::Bitmap => 4,
+        }
+    }
+}
+
+/// Lets atlases grow, instead of a new one being started as soon as one is
+/// full. Hand one out from
+/// [`AtlasHandler::growth`](trait.AtlasHandler.html#method.growth).
+pub trait AtlasGrowth<AtlasID, E> {
+    /// Return the largest size that atlases in the given group may grow to.
+    /// If this is bigger than `get_atlas_size`, a full atlas will be grown
+    /// with [`resize_atlas`](#tymethod.resize_atlas) (doubling its height or
+    /// width at a time) before a new atlas is created.
+    fn get_max_atlas_size(&mut self, group: usize) -> (u32, u32);
+    /// Enlarge an atlas to the given size. Texels that are already in the
+    /// atlas must stay where they are; the new area can start out blank.
+    ///
+    /// In [`AtlasMode::Layers`], every layer of a texture array shares one
+    /// size, so this resizes the whole array that `atlas` is a layer of.
+    ///
+    /// [`AtlasMode::Layers`]: enum.AtlasMode.html#variant.Layers
+    fn resize_atlas(&mut self, atlas: AtlasID, new_width: u32, new_height: u32)
+        -> Result<(), E>;
+}
 
 pub trait AtlasHandler {
     type AtlasID : Copy;
     type AtlasCoords : Copy;
     type E;
-    /// Create a new, blank atlas.
-    fn new_atlas(&mut self) -> Result<Self::AtlasID, Self::E>;
-    /// Return the size of the atlases that this handler will create. We call
-    /// this a lot, so if determining this value is expensive, cache it!
-    fn get_atlas_size(&mut self) -> (u32, u32);
+    /// Create a new, blank atlas for the given group, that will hold texels
+    /// of the given format. Glyphs of one group or format will never be put
+    /// into an atlas of a different group or format.
+    ///
+    /// Groups are numbers you assign to faces with
+    /// [`set_atlas_group`](struct.TextHandler.html#method.set_atlas_group).
+    /// Faces start out in group 0. If you only have one group, you can
+    /// ignore this parameter.
+    fn new_atlas(&mut self, group: usize, format: AtlasFormat)
+        -> Result<Self::AtlasID, Self::E>;
+    /// Add a new, blank layer to the texture array for the given group and
+    /// format. `layer` counts up from zero, separately for each group and
+    /// format, and will never reach the limit given in
+    /// [`AtlasMode::Layers`]. `width` and `height` are the size of the
+    /// array's existing layers, or `get_atlas_size` if this is the first.
+    ///
+    /// Only called in [`AtlasMode::Layers`], instead of `new_atlas`. The
+    /// default calls `new_atlas`.
+    ///
+    /// [`AtlasMode::Layers`]: enum.AtlasMode.html#variant.Layers
+    fn new_atlas_layer(&mut self, group: usize, format: AtlasFormat,
+                       layer: u32, width: u32, height: u32)
+        -> Result<Self::AtlasID, Self::E> {
+        let _ = (layer, width, height);
+        self.new_atlas(group, format)
+    }
+    /// Return the size of the atlases that this handler will create for the
+    /// given group. We call this a lot, so if determining this value is
+    /// expensive, cache it!
+    fn get_atlas_size(&mut self, group: usize) -> (u32, u32);
+    /// If your atlases can grow, return something that grows them (most
+    /// likely `Some(self)`). A full atlas will then be grown, up to
+    /// [`AtlasGrowth::get_max_atlas_size`], before a new atlas is created,
+    /// which saves you from switching textures as often.
+    ///
+    /// The default returns `None`, so atlases never grow.
+    ///
+    /// [`AtlasGrowth::get_max_atlas_size`]: trait.AtlasGrowth.html#tymethod.get_max_atlas_size
+    fn growth(&mut self)
+        -> Option<&mut dyn AtlasGrowth<Self::AtlasID, Self::E>> {
+        None
+    }
+    /// Called for every glyph in an atlas after that atlas is resized, with
+    /// the `AtlasCoords` you returned from `add_to_atlas` and everything
+    /// else we know about the glyph. Return new `AtlasCoords`, for instance
+    /// if yours contain texture coordinates that are normalized to the size
+    /// of the atlas.
+    ///
+    /// The default returns `coords` unchanged, which is right if your
+    /// `AtlasCoords` are in texels.
+    fn rederive_coords(&mut self, atlas: Self::AtlasID,
+                       coords: Self::AtlasCoords, info: &GlyphInfo)
+        -> Result<Self::AtlasCoords, Self::E> {
+        let _ = (atlas, info);
+        Ok(coords)
+    }
     /// This function performs two operations:
     ///
     /// 1. Upload the given glyph pixels to the given region of the given
@@ -108,7 +230,20 @@ pub trait AtlasHandler {
     /// 2. Return an `AtlasCoords` that provide enough information to later
     ///    render this glyph.
     ///
+    /// `glyph_pixels` is in the format that `target_atlas` was created with.
+    /// Rows go from the bottom of the glyph (`render_y_min`) to the top.
+    ///
+    /// `TextHandler` remembers all of these parameters, and will give them
+    /// back to you from
+    /// [`get_glyph_info`](struct.TextHandler.html#method.get_glyph_info), so
+    /// you don't have to stuff them all into `AtlasCoords`.
+    ///
     /// (Don't forget to account for the half-texel borders!)
+    ///
+    /// If atlases can grow, `AtlasCoords` may be replaced by
+    /// [`rederive_coords`](#method.rederive_coords) later on, so don't hold
+    /// on to ones you got from `get_glyph` for longer than a frame.
+    #[allow(clippy::too_many_arguments)]
     fn add_to_atlas(&mut self,
                     target_atlas: Self::AtlasID,
                     render_x_min: f32, render_y_min: f32,
@@ -118,133 +253,945 @@ pub trait AtlasHandler {
                     glyph_pixels: &[u8]) -> Result<Self::AtlasCoords, Self::E>;
 }
 
+/// What to do with a glyph that is too big to fit in an atlas at the face's
+/// texel density. Set with
+/// [`set_oversize_glyphs`](struct.TextHandler.html#method.set_oversize_glyphs).
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum OversizeGlyphs {
+    /// Render the glyph at a lower texel density, just low enough that it
+    /// fits. The glyph's render bounds are unaffected, but it will look
+    /// blurrier than its neighbors. This is the default.
+    #[default]
+    Shrink,
+    /// Don't render the glyph at all. `get_glyph` will return
+    /// [`Error::GlyphTooLarge`](enum.Error.html#variant.GlyphTooLarge).
+    Reject,
+}
+
+/// How atlases map onto textures. Set with
+/// [`set_atlas_mode`](struct.TextHandler.html#method.set_atlas_mode).
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum AtlasMode {
+    /// Every atlas is its own texture, and there's no limit on how many there
+    /// can be. This is the default.
+    #[default]
+    Separate,
+    /// Atlases are layers of a texture array, one array per group and
+    /// format, so that text spanning several atlases can be drawn in one
+    /// call. New layers are made with
+    /// [`new_atlas_layer`](trait.AtlasHandler.html#method.new_atlas_layer),
+    /// and a glyph's layer is in its
+    /// [`GlyphInfo::atlas_layer`](struct.GlyphInfo.html#structfield.atlas_layer).
+    ///
+    /// Each array holds at most `max_layers` layers. A glyph that doesn't
+    /// fit once they're all full gets
+    /// [`Error::OutOfLayers`](enum.Error.html#variant.OutOfLayers).
+    Layers { max_layers: u32 },
+}
+
+/// An error from [`get_glyph`](struct.TextHandler.html#method.get_glyph) or
+/// one of its friends.
+#[derive(Debug)]
+pub enum Error<E> {
+    /// Your `AtlasHandler` returned an error.
+    Atlas(E),
+    /// The glyph's rendering would have been `width`×`height` texels, which
+    /// doesn't fit in an atlas, and [`OversizeGlyphs::Reject`] is in effect.
+    /// (Or the atlases are too small to hold even the padding.)
+    ///
+    /// [`OversizeGlyphs::Reject`]: enum.OversizeGlyphs.html#variant.Reject
+    GlyphTooLarge { face: usize, glyph: u16, width: u32, height: u32 },
+    /// In [`AtlasMode::Layers`], the texture array for the given group and
+    /// format already has as many layers as it's allowed, and they're full.
+    ///
+    /// [`AtlasMode::Layers`]: enum.AtlasMode.html#variant.Layers
+    OutOfLayers { group: usize, format: AtlasFormat },
+    /// There's no face with the given index, or it has been removed.
+    NoSuchFace { face: usize },
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Atlas(x) => write!(f, "atlas error: {}", x),
+            Error::GlyphTooLarge { face, glyph, width, height } => {
+                write!(f, "glyph {} of face {} would be {}x{} texels, which \
+                           doesn't fit in an atlas", glyph, face, width,
+                       height)
+            },
+            Error::OutOfLayers { group, format } => {
+                write!(f, "no layers left for {:?} glyphs in atlas group {}",
+                       format, group)
+            },
+            Error::NoSuchFace { face } => write!(f, "no face {}", face),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for Error<E> {}
+
+/// What glyph lookups return: `Ok(None)` for a glyph that has nothing to
+/// show (yet), `Ok(Some(...))` for a glyph that's ready to draw.
+pub type GlyphResult<T, E> = Result<Option<T>, Error<E>>;
+
+/// One layer of a color glyph, as returned by
+/// [`get_color_glyph`](struct.TextHandler.html#method.get_color_glyph). Draw
+/// each layer as you would draw an ordinary glyph, tinted with `color`.
+#[derive(Clone,Copy,Debug)]
+pub struct ColorLayer<AtlasID: Copy, AtlasCoords: Copy> {
+    /// The glyph ID of the outline making up this layer.
+    pub glyph: u16,
+    /// The color of this layer, as non-premultiplied RGBA. `None` means
+    /// "whatever color you would have drawn this text in".
+    pub color: Option<[u8; 4]>,
+    pub atlas: AtlasID,
+    pub coords: AtlasCoords,
+}
+
+/// Space to leave around glyphs in an atlas. Set with
+/// [`set_atlas_spacing`](struct.TextHandler.html#method.set_atlas_spacing).
+///
+/// This is on top of each glyph's own padding (see
+/// [`RenderParams`](struct.RenderParams.html)), which is part of its
+/// distance field. This space is left untouched by `add_to_atlas`, and
+/// keeps glyphs from bleeding into each other when the atlas is sampled
+/// with linear filtering or mipmaps.
 #[derive(Clone,Copy,Debug,PartialEq,Eq)]
-struct Rect {
-    x: u32, y: u32, w: u32, h: u32,
+pub struct AtlasSpacing {
+    /// Texels of space between neighboring glyphs.
+    pub between_glyphs: u32,
+    /// Texels of space between glyphs and the edges of the atlas.
+    pub border: u32,
+    /// Glyphs start on multiples of this many texels (on both axes), and
+    /// take up a whole multiple of it, spacing included. Set this to the
+    /// block size (usually 4) of a block-compressed texture format, so that
+    /// no block holds parts of two glyphs. Zero is treated as one.
+    pub alignment: u32,
+}
+
+impl Default for AtlasSpacing {
+    fn default() -> AtlasSpacing {
+        AtlasSpacing { between_glyphs: 0, border: 0, alignment: 1 }
+    }
+}
+
+impl AtlasSpacing {
+    fn alignment(&self) -> u32 {
+        self.alignment.max(1)
+    }
+    /// Where the first glyph can go, on both axes: the border, rounded up
+    /// to the alignment.
+    fn origin(&self) -> u32 {
+        self.border.div_ceil(self.alignment()) * self.alignment()
+    }
+    /// Returns the size of the area that the packer works in, in units of
+    /// `alignment`, for an atlas of the given size. Each glyph takes up its
+    /// own size plus `between_glyphs`, so an extra `between_glyphs` fits
+    /// at the far edges.
+    fn packer_size(&self, w: u32, h: u32) -> (u32, u32) {
+        let margin = self.origin() + self.border;
+        ((w.saturating_sub(margin) + self.between_glyphs) / self.alignment(),
+         (h.saturating_sub(margin) + self.between_glyphs) / self.alignment())
+    }
+    /// Returns the largest glyph that fits in an atlas of the given size.
+    fn usable_size(&self, w: u32, h: u32) -> (u32, u32) {
+        let (w, h) = self.packer_size(w, h);
+        ((w * self.alignment()).saturating_sub(self.between_glyphs),
+         (h * self.alignment()).saturating_sub(self.between_glyphs))
+    }
 }
 
 struct AtlasState<AtlasID: Copy> {
     handle: AtlasID,
-    packer: Packer,
+    group: usize,
+    format: AtlasFormat,
+    /// How many atlases of the same group and format came before this one.
+    layer: u32,
+    /// The spacing and packing this atlas was made with.
+    spacing: AtlasSpacing,
+    packing: Packing,
+    size: (u32, u32),
+    /// Works in units of `spacing.alignment`, within the border.
+    packer: Box<dyn AtlasPacker>,
+    /// Texels covered by glyphs.
+    used_texels: u64,
+    glyphs: usize,
 }
 
 impl<AtlasID: Copy> AtlasState<AtlasID> {
-    pub fn new(handle: AtlasID, w: u32, h: u32) -> AtlasState<AtlasID>{
+    pub fn new(handle: AtlasID, group: usize, format: AtlasFormat,
+               layer: u32, options: &AtlasOptions, w: u32, h: u32)
+        -> AtlasState<AtlasID>{
+        let (packer_w, packer_h) = options.spacing.packer_size(w, h);
         AtlasState {
             handle,
-            packer: Packer::new(rect_packer::Config {
-                width: w as i32, height: h as i32,
-                border_padding: 0, rectangle_padding: 0,
-            }),
+            group,
+            format,
+            layer,
+            spacing: options.spacing,
+            packing: options.packing,
+            size: (w, h),
+            packer: options.packing.new_packer(packer_w, packer_h),
+            used_texels: 0,
+            glyphs: 0,
         }
     }
     pub fn attempt_fit(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
-        match self.packer.pack(w as i32, h as i32, false) {
-            Some(rect) => Some((rect.x as u32, rect.y as u32)),
-            None => None,
+        let alignment = self.spacing.alignment();
+        let (x, y) = self.packer.pack(
+            (w + self.spacing.between_glyphs).div_ceil(alignment),
+            (h + self.spacing.between_glyphs).div_ceil(alignment))?;
+        self.used_texels += w as u64 * h as u64;
+        self.glyphs += 1;
+        let origin = self.spacing.origin();
+        Some((origin + x * alignment, origin + y * alignment))
+    }
+    /// Returns the size this atlas should grow to next, or `None` if it's
+    /// already as big as `max` allows. Grows the height first, then the
+    /// width, doubling one at a time.
+    pub fn next_size(&self, (max_w, max_h): (u32, u32)) -> Option<(u32, u32)> {
+        let (w, h) = self.size();
+        if h < max_h && (h <= w || w >= max_w) {
+            Some((w, (h * 2).clamp(1, max_h)))
+        }
+        else if w < max_w {
+            Some(((w * 2).clamp(1, max_w), h))
+        }
+        else { None }
+    }
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+    pub fn resize(&mut self, w: u32, h: u32) {
+        self.size = (w, h);
+        let (packer_w, packer_h) = self.spacing.packer_size(w, h);
+        self.packer.resize(packer_w, packer_h);
+    }
+    /// Gives back the space taken by a glyph that `attempt_fit` found room
+    /// for. If that was the last glyph, the packer starts over.
+    pub fn free(&mut self, rect: Rect) {
+        self.used_texels -= rect.w as u64 * rect.h as u64;
+        self.glyphs -= 1;
+        if self.glyphs == 0 {
+            let (packer_w, packer_h) = self.spacing.packer_size(self.size.0,
+                                                                self.size.1);
+            self.packer = self.packing.new_packer(packer_w, packer_h);
+        }
+        else {
+            let alignment = self.spacing.alignment();
+            let origin = self.spacing.origin();
+            let between = self.spacing.between_glyphs;
+            self.packer.free((rect.x - origin) / alignment,
+                             (rect.y - origin) / alignment,
+                             (rect.w + between).div_ceil(alignment),
+                             (rect.h + between).div_ceil(alignment));
+        }
+    }
+    /// The largest glyph that would still fit without growing, according to
+    /// the packer.
+    pub fn largest_free(&self) -> Option<(u32, u32)> {
+        let (w, h) = self.packer.largest_free_rect()?;
+        let alignment = self.spacing.alignment();
+        Some(((w * alignment).saturating_sub(self.spacing.between_glyphs),
+              (h * alignment).saturating_sub(self.spacing.between_glyphs)))
+    }
+}
+
+/// How full an atlas is. Returned by
+/// [`get_atlas_occupancy`](struct.TextHandler.html#method.get_atlas_occupancy).
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct AtlasOccupancy<AtlasID: Copy> {
+    pub atlas: AtlasID,
+    pub group: usize,
+    pub format: AtlasFormat,
+    /// See [`GlyphInfo::atlas_layer`](struct.GlyphInfo.html#structfield.atlas_layer).
+    pub layer: u32,
+    /// The atlas's current size, in texels.
+    pub width: u32, pub height: u32,
+    /// How many texels are covered by glyphs (including their padding).
+    pub used_texels: u64,
+    /// How many glyphs are in the atlas.
+    pub glyphs: usize,
+    /// The size, in texels, of the largest glyph (padding included) that
+    /// would still fit without growing the atlas. This is an estimate, made
+    /// by the atlas's packer; `None` if a custom packer doesn't say.
+    pub largest_free: Option<(u32, u32)>,
+}
+
+impl<AtlasID: Copy> AtlasOccupancy<AtlasID> {
+    /// The fraction of the atlas that's covered by glyphs, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        let total = self.width as u64 * self.height as u64;
+        if total == 0 { 0.0 }
+        else { (self.used_texels as f64 / total as f64) as f32 }
+    }
+}
+
+/// How much rendering has been done, and how long it took. Part of
+/// [`TextStats`](struct.TextStats.html).
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct RenderStats {
+    /// Glyphs rendered on the calling thread, including ones that turned
+    /// out to be empty, missing or too large.
+    pub foreground_renders: u64,
+    /// Glyphs rendered on the background thread, likewise.
+    pub background_renders: u64,
+    /// Total time spent rendering on the calling thread.
+    pub foreground_time: Duration,
+    /// Total time spent rendering on the background thread.
+    pub background_time: Duration,
+    /// Total size of all the glyph images rendered, in bytes. This is how
+    /// much has been handed to `add_to_atlas`, give or take glyphs that
+    /// couldn't be put into an atlas.
+    pub rendered_bytes: u64,
+}
+
+impl RenderStats {
+    fn count(&mut self, result: &Result<RenderResult, TooLarge>,
+             time: Duration, background: bool) {
+        if background {
+            self.background_renders += 1;
+            self.background_time += time;
+        }
+        else {
+            self.foreground_renders += 1;
+            self.foreground_time += time;
+        }
+        if let Ok(RenderResult::Rendered(rendered)) = result {
+            self.rendered_bytes += rendered.pixels.len() as u64;
         }
     }
 }
 
+/// Everything there is to know about how a `TextHandler` is using its
+/// atlases. Returned by [`stats`](struct.TextHandler.html#method.stats).
+#[derive(Clone,Debug)]
+pub struct TextStats<AtlasID: Copy> {
+    /// Every atlas, in the order they were made. (The same as
+    /// [`get_atlas_occupancy`](struct.TextHandler.html#method.get_atlas_occupancy).)
+    pub atlases: Vec<AtlasOccupancy<AtlasID>>,
+    pub rendering: RenderStats,
+}
+
+/// A glyph in the cache, and where it is. Returned by
+/// [`cached_glyphs`](struct.TextHandler.html#method.cached_glyphs).
+#[derive(Clone,Copy,Debug)]
+pub struct CachedGlyph<'a, AtlasID: Copy, AtlasCoords: Copy> {
+    pub face: usize,
+    pub glyph: u16,
+    /// The atlas group the glyph was rendered for. (This can differ from
+    /// the face's current group, if the face has been moved since.)
+    pub group: usize,
+    pub atlas: AtlasID,
+    pub coords: AtlasCoords,
+    /// The glyph's density tier. See
+    /// [`set_density_tiers`](struct.TextHandler.html#method.set_density_tiers).
+    pub tier: f32,
+    pub info: &'a GlyphInfo,
+}
+
+/// Settings that affect how glyphs are put into atlases.
+#[derive(Clone,Copy,Debug,Default)]
+struct AtlasOptions {
+    mode: AtlasMode,
+    packing: Packing,
+    spacing: AtlasSpacing,
+}
+
 struct GlyphState<AtlasID: Copy, AtlasCoords: Copy> {
+    /// Index into `TextHandler::atlases`.
+    atlas_index: usize,
     atlas: AtlasID,
     coords: AtlasCoords,
+    info: GlyphInfo,
+}
+
+/// A rectangle of texels within an atlas.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Rect {
+    pub x: u32, pub y: u32, pub w: u32, pub h: u32,
+}
+
+/// Everything we know about a glyph that's been put into an atlas. Returned
+/// by [`get_glyph_info`](struct.TextHandler.html#method.get_glyph_info).
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct GlyphInfo {
+    /// The edges of the quad to draw this glyph with, in ems, relative to
+    /// the pen position. These are the same as the `render_*` parameters
+    /// given to `add_to_atlas`, and include the padding.
+    pub render_x_min: f32, pub render_y_min: f32,
+    pub render_x_max: f32, pub render_y_max: f32,
+    /// Where in its atlas the glyph is, in texels.
+    pub atlas_rect: Rect,
+    /// Which atlas of its group and format the glyph is in, counting from
+    /// zero in the order they were made. In [`AtlasMode::Layers`], this is
+    /// the layer of the texture array.
+    ///
+    /// [`AtlasMode::Layers`]: enum.AtlasMode.html#variant.Layers
+    pub atlas_layer: u32,
+    /// What kind of atlas the glyph is in.
+    pub format: AtlasFormat,
+    pub metrics: GlyphMetrics,
+    /// The distance range of the SDF, in texels: the distance between the
+    /// points where a channel reads fully "outside" and fully "inside". Zero
+    /// for bitmap glyphs. Usually the face's
+    /// [`RenderParams::distance_range`], but wider for synthetic bold faces
+    /// (see [`SyntheticStyle::embolden`]).
+    ///
+    /// [`RenderParams::distance_range`]: struct.RenderParams.html#structfield.distance_range
+    /// [`SyntheticStyle::embolden`]: struct.SyntheticStyle.html#structfield.embolden
+    pub distance_range_texels: f32,
+    /// The same distance range, converted to ems along each axis. (These
+    /// differ if the face has different horizontal and vertical texel
+    /// densities, or if the glyph had to be shrunk.)
+    pub distance_range_ems_x: f32,
+    pub distance_range_ems_y: f32,
+}
+
+/// Horizontal metrics of a glyph, in ems. Returned by
+/// [`get_glyph_metrics`](struct.TextHandler.html#method.get_glyph_metrics).
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct GlyphMetrics {
+    /// How far to move the pen after drawing this glyph.
+    pub advance: f32,
+    /// Distance from the pen position to the left edge of the glyph's
+    /// outline.
+    pub left_side_bearing: f32,
+    /// Distance from the right edge of the glyph's outline to the pen
+    /// position after the advance.
+    pub right_side_bearing: f32,
+}
+
+/// The size that a glyph would have been rendered at, if it weren't too big
+/// for an atlas.
+struct TooLarge {
+    width: u32, height: u32,
 }
 
+/// A glyph that has been rendered, but not yet put into an atlas. Fields are
+/// in the same order as they are passed to `add_to_atlas`, except that
+/// `atlas_x` and `atlas_y` are missing.
+struct RenderedGlyph {
+    render_x_min: f32, render_y_min: f32,
+    render_x_max: f32, render_y_max: f32,
+    sdf_width_int: u32, sdf_height_int: u32,
+    /// Which kind of atlas `pixels` belongs in.
+    format: AtlasFormat,
+    pixels: Vec<u8>,
+    metrics: GlyphMetrics,
+    /// In texels. Zero if this isn't a distance field.
+    distance_range: f32,
+}
+
+/// What came of trying to render a glyph.
+enum RenderResult {
+    /// The glyph isn't in the font, or is in a form we can't render.
+    Missing,
+    /// The glyph is in the font, but has nothing to draw (e.g. a space).
+    Empty(GlyphMetrics),
+    Rendered(RenderedGlyph),
+}
+
+/// Which algorithm to use to assign colors to the edges of a glyph's outline.
+/// More will be added as `fdsm` grows them.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Hash)]
+#[non_exhaustive]
+pub enum EdgeColoring {
+    /// The simple edge coloring algorithm from Chlumský's thesis. Good for
+    /// most text.
+    #[default]
+    Simple,
+}
+
+/// How to decide which parts of a glyph's outline are filled, when contours
+/// overlap or intersect themselves. Faces start out with `NonZero`, which is
+/// what both TrueType and CFF outlines are specified to use.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Hash)]
+pub enum FillRule {
+    /// A point is inside if the contours wind around it a nonzero number of
+    /// times. TrueType, CFF and CFF2 outlines are made to be filled this way.
+    #[default]
+    NonZero,
+    /// A point is inside if it's enclosed by an odd number of contours. Some
+    /// converted and icon fonts depend on this.
+    EvenOdd,
+}
+
+impl FillRule {
+    fn to_fdsm(self) -> fdsm::bezier::scanline::FillRule {
+        match self {
+            FillRule::NonZero => fdsm::bezier::scanline::FillRule::Nonzero,
+            FillRule::EvenOdd => fdsm::bezier::scanline::FillRule::Odd,
+        }
+    }
+}
+
+/// Whether to clean up MSDF artifacts after generation. MSDFs can have
+/// "clashes", texels whose channels disagree with their neighbors' in a way
+/// that makes stray pixels appear when rendered, particularly around acute
+/// corners at small sizes. Correction finds these and falls back to the
+/// ordinary single-channel distance there.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Hash)]
+pub enum ErrorCorrection {
+    /// Leave the MSDF as generated.
+    #[default]
+    Off,
+    /// Correct clashes, except right next to corners, where flattening the
+    /// channels would round the corner off. A good default if you're seeing
+    /// artifacts.
+    EdgePriority,
+    /// Correct every clash, even at the cost of slightly softer corners.
+    Full,
+}
+
+/// Knobs for MSDF generation, set per face with
+/// [`set_msdf_config`](struct.TextHandler.html#method.set_msdf_config).
+/// Sharp-cornered display fonts and icon fonts can sometimes be rid of
+/// artifacts by tweaking these.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct MsdfConfig {
+    pub edge_coloring: EdgeColoring,
+    /// Edges meeting at an angle sharper than this (in radians) are
+    /// considered a corner, and given different colors. Only 0 to π/2 make
+    /// sense; other values are clamped to that range.
+    pub corner_angle_threshold: f64,
+    /// Seed for the pseudorandom choices made while coloring edges.
+    pub seed: u64,
+    pub fill_rule: FillRule,
+    pub error_correction: ErrorCorrection,
+    /// Also render the true distance into an alpha channel, making glyphs
+    /// [`AtlasFormat::Mtsdf`](enum.AtlasFormat.html#variant.Mtsdf) instead
+    /// of `Msdf`. They'll go into their own atlases, which you may want to
+    /// put in their own group.
+    pub mtsdf: bool,
+}
+
+impl Default for MsdfConfig {
+    fn default() -> MsdfConfig {
+        MsdfConfig {
+            edge_coloring: EdgeColoring::Simple,
+            // what we used before this was configurable
+            corner_angle_threshold: 0.3f64.asin(),
+            seed: 8, // Admiral's favorite u64, apparently
+            fill_rule: FillRule::NonZero,
+            error_correction: ErrorCorrection::Off,
+            mtsdf: false,
+        }
+    }
+}
+
+/// A hashable stand-in for an `MsdfConfig`, so that glyphs rendered with
+/// different settings get different entries in the cache.
+type MsdfKey = (EdgeColoring, u64, u64, FillRule, ErrorCorrection, bool);
+
+impl MsdfConfig {
+    fn cache_key(&self) -> MsdfKey {
+        (self.edge_coloring, self.corner_angle_threshold().to_bits(),
+         self.seed, self.fill_rule, self.error_correction, self.mtsdf)
+    }
+    /// The corner angle threshold, clamped to the range where it means
+    /// something. (It's passed on as its sine, so angles past a right angle
+    /// would act like smaller ones.)
+    fn corner_angle_threshold(&self) -> f64 {
+        self.corner_angle_threshold.clamp(0.0, std::f64::consts::FRAC_PI_2)
+    }
+}
+
+/// Synthetic styling, for faking a bold or oblique style that a font doesn't
+/// actually come with. See
+/// [`add_face_variant`](struct.TextHandler.html#method.add_face_variant).
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct SyntheticStyle {
+    /// How far to push the outline outwards, in font units. Typical fake bold
+    /// is somewhere around 2% of the font's units per em. The distance range
+    /// of the glyphs is widened by twice this (in texels), so that the whole
+    /// range is still there around the new outline; see
+    /// [`GlyphInfo::distance_range_texels`].
+    ///
+    /// [`GlyphInfo::distance_range_texels`]: struct.GlyphInfo.html#structfield.distance_range_texels
+    pub embolden: f32,
+    /// How far to slant the glyph to the right, as a horizontal offset per
+    /// unit of height. Typical fake oblique is around 0.2 (about 12°).
+    /// Negative values slant to the left.
+    pub skew: f32,
+}
+
+/// How a face's glyphs are laid out in the atlas. See
+/// [`add_face_with_params`](struct.TextHandler.html#method.add_face_with_params).
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct RenderParams {
+    /// The distance range of the SDFs, in texels: the distance between the
+    /// points where a channel reads fully "outside" and fully "inside".
+    /// Effects that reach away from the outline (thick outlines, glows,
+    /// shadows) need a bigger range. Values less than 2.0 are suicide!
+    pub distance_range: f32,
+    /// The number of texels of empty space to leave on each side of a
+    /// glyph's outline. The distance field doesn't extend past this, so
+    /// effects that reach further than the padding get cut off at the edge of
+    /// the glyph's quad. Smaller padding packs tighter.
+    pub padding: f32,
+    /// The number of texels that a single em in the given font should occupy
+    /// in the atlas. This should be experimentally determined per font. 64
+    /// is usually a good starting point. Thinner fonts will need higher
+    /// values. (Or see [`auto`](#method.auto).)
+    pub texels_per_em_x: f32,
+    pub texels_per_em_y: f32,
+}
+
+impl RenderParams {
+    /// Makes the parameters that [`add_face`] uses: a distance range of
+    /// `border_texels`, and padding of half that on each side, so the
+    /// distance field reaches exactly to the edges of each glyph's quad.
+    ///
+    /// [`add_face`]: struct.TextHandler.html#method.add_face
+    pub fn from_border(border_texels: f32,
+                       texels_per_em_x: f32, texels_per_em_y: f32)
+        -> RenderParams {
+        RenderParams {
+            distance_range: border_texels,
+            padding: border_texels * 0.5,
+            texels_per_em_x, texels_per_em_y,
+        }
+    }
+    /// Works out how many texels per em a face needs for its thinnest
+    /// strokes to be at least `min_stroke_texels` across. 2 is about the
+    /// least that holds up; 3 or 4 leaves room for effects and for sharper
+    /// corners.
+    ///
+    /// The strokes are measured from the outlines of a few representative
+    /// glyphs ('l', 'o', 'H', 'I' and 'n'), using whichever of them the face
+    /// has, with its current variations. Returns `None` if it has none of
+    /// them, as icon and symbol fonts often don't. Slivers less than a
+    /// hundredth of an em across, as overlapping contours can leave, are
+    /// ignored, and the estimate is kept between 8 and 256 texels per em,
+    /// so that one odd glyph can't make every glyph huge.
+    pub fn estimate_texels_per_em(face: &ttf_parser::Face,
+                                  min_stroke_texels: f32)
+        -> Option<f32> {
+        let thinnest = stems::thinnest_stroke(face)?;
+        Some((min_stroke_texels * face.units_per_em() as f32 / thinnest)
+             .clamp(8.0, 256.0))
+    }
+    /// As [`from_border`](#method.from_border), but with the texel density
+    /// chosen by
+    /// [`estimate_texels_per_em`](#method.estimate_texels_per_em).
+    pub fn auto(face: &ttf_parser::Face, border_texels: f32,
+                min_stroke_texels: f32)
+        -> Option<RenderParams> {
+        let texels_per_em = RenderParams::estimate_texels_per_em(
+            face, min_stroke_texels)?;
+        Some(RenderParams::from_border(border_texels,
+                                       texels_per_em, texels_per_em))
+    }
+}
+
+#[derive(Clone)]
 struct FaceState {
-    /// This field is what `*_face` actually borrows from. `Arc` doesn't provide
-    /// interior mutability, and without interior mutability the allocated
-    /// block will never move, so this is *sound* (but not *safe*), as long as
-    /// `*_face` is never moved out of us.
-    _face_data: Arc<Vec<u8>>,
-    face: Face<'static>,
-    border_texels: f32,
-    texels_per_em_x: f32,
-    texels_per_em_y: f32,
+    /// The parsed face, along with the font data it borrows from.
+    owned: OwnedFace,
+    /// The index of this face within its font data, in case it's a
+    /// collection.
+    index: u32,
+    /// The variations the face was added with, to apply again if the font
+    /// data is replaced.
+    variations: Vec<Variation>,
+    /// Goes up every time the font data is replaced, so that glyphs
+    /// rendered in the background from the old data can be told apart.
+    revision: u64,
+    params: RenderParams,
+    /// Density tiers, as multiples of `params`' texel densities, in
+    /// ascending order. Always includes 1.0.
+    tiers: Vec<f32>,
+    synthetic: SyntheticStyle,
+    msdf: MsdfConfig,
+    atlas_group: usize,
 }
 
 impl FaceState {
+    fn face(&self) -> &Face<'_> { self.owned.face() }
+    /// The key under which the given glyph of this face (which has the given
+    /// index) would currently be cached, in the given density tier.
+    fn glyph_key(&self, face: usize, glyph: u16, tier: f32) -> GlyphKey {
+        (face, glyph, self.atlas_group, self.msdf.cache_key(), tier.to_bits())
+    }
+    /// Picks the density tier to draw glyphs at the given size with: the
+    /// lowest that has at least one texel per pixel vertically, or the
+    /// highest if none does.
+    fn tier_for_size(&self, pixels_per_em: f32) -> f32 {
+        let tiers = &self.tiers;
+        tiers.iter().copied()
+            .find(|tier| self.params.texels_per_em_y * tier >= pixels_per_em)
+            .unwrap_or(tiers[tiers.len() - 1])
+    }
     /// Renders a glyph into an MSDF. Returns enough information to add the
-    /// glyph to the atlas.
+    /// glyph to the atlas. If the glyph has no outline, but does have a
+    /// raster image, returns that image instead (see `render_raster_glyph`).
     ///
-    /// Returned values are in the same order as they are passed to
-    /// `add_to_atlas`, except that `atlas_x` and `atlas_y` are missing.
+    /// The texel densities are multiplied by `tier` (see `tiers`).
     ///
-    /// Returns `None` if the given glyph is not present in the font, or if it
-    /// has no actual shape.
-    pub fn render_glyph(&self, glyph: GlyphId, atlas_w: u32, atlas_h: u32)
-        -> Option<(f32, f32, f32, f32, u32, u32, RgbImage)> {
-        let mut shape = Shape::load_from_face(&self.face, glyph);
-        let bbox = match self.face.glyph_bounding_box(glyph) {
+    /// Returns `Err` if the glyph doesn't fit in an atlas and `oversize` says
+    /// not to shrink it.
+    pub fn render_glyph(&self, glyph: GlyphId, tier: f32,
+                        atlas_w: u32, atlas_h: u32, oversize: OversizeGlyphs)
+        -> Result<RenderResult, TooLarge> {
+        if glyph.0 >= self.face().number_of_glyphs() {
+            return Ok(RenderResult::Missing)
+        }
+        let bbox = match self.face().glyph_bounding_box(glyph) {
             Some(bbox) => bbox,
-            None => {
-                warn!("psilo-font only supports outline glyphs, but this \
-                       font seems to contain an image glyph");
-                return None;
-            }
+            None => return self.render_raster_glyph(glyph, tier,
+                                                    atlas_w, atlas_h,
+                                                    oversize),
+        };
+        let metrics = self.metrics(glyph, bbox.x_min as f32,
+                                   bbox.x_max as f32);
+        let per_em = self.face().units_per_em() as f32;
+        // Grow the bounding box to account for synthetic styling. The skew
+        // pushes the top and bottom of the glyph in opposite directions, so
+        // the box is widened by both.
+        let embolden = self.synthetic.embolden.max(0.0);
+        let skew = self.synthetic.skew;
+        let skewed_bottom = bbox.y_min as f32 * skew;
+        let skewed_top = bbox.y_max as f32 * skew;
+        let bbox_x_min = bbox.x_min as f32 + skewed_bottom.min(skewed_top)
+            - embolden;
+        let bbox_x_max = bbox.x_max as f32 + skewed_bottom.max(skewed_top)
+            + embolden;
+        let bbox_y_min = bbox.y_min as f32 - embolden;
+        let bbox_y_max = bbox.y_max as f32 + embolden;
+        let raw_glyph_width = bbox_x_max - bbox_x_min;
+        let raw_glyph_height = bbox_y_max - bbox_y_min;
+        // An outline that encloses no area (all its points in a line, or
+        // even a single point) has nothing to draw, and would have us divide
+        // by zero below.
+        if raw_glyph_width <= 0.0 || raw_glyph_height <= 0.0 {
+            return Ok(RenderResult::Empty(metrics))
+        }
+        let mut shape = Shape::load_from_face(self.face(), glyph);
+        let params = &RenderParams {
+            texels_per_em_x: self.params.texels_per_em_x * tier,
+            texels_per_em_y: self.params.texels_per_em_y * tier,
+            ..self.params
         };
-        let per_em = self.face.units_per_em() as f32;
-        let raw_glyph_width = (bbox.x_max - bbox.x_min) as f32;
-        let raw_glyph_height = (bbox.y_max - bbox.y_min) as f32;
-        let glyph_width = raw_glyph_width
-            * self.texels_per_em_x / per_em;
-        let glyph_height = raw_glyph_height
-            * self.texels_per_em_y / per_em;
-        let sdf_width = (glyph_width + self.border_texels).ceil();
-        let sdf_height = (glyph_height + self.border_texels).ceil();
-        let wrangled_glyph_width = sdf_width - self.border_texels;
-        let wrangled_glyph_height = sdf_height - self.border_texels;
-        let sdf_width_int = (sdf_width.ceil() as u32).min(atlas_w);
-        let sdf_height_int = (sdf_height.ceil() as u32).min(atlas_h);
+        // padding on both sides
+        let padding = params.padding.max(0.0) * 2.0;
+        let mut glyph_width = raw_glyph_width
+            * params.texels_per_em_x / per_em;
+        let mut glyph_height = raw_glyph_height
+            * params.texels_per_em_y / per_em;
+        let too_large = TooLarge {
+            width: (glyph_width + padding).ceil() as u32,
+            height: (glyph_height + padding).ceil() as u32,
+        };
+        if too_large.width > atlas_w || too_large.height > atlas_h {
+            // The padding is in texels, like the distance range, so it can't
+            // shrink along with the glyph.
+            let room_x = atlas_w as f32 - padding;
+            let room_y = atlas_h as f32 - padding;
+            if oversize == OversizeGlyphs::Reject
+            || room_x < 1.0 || room_y < 1.0 {
+                return Err(too_large);
+            }
+            let factor = (room_x / glyph_width).min(room_y / glyph_height);
+            glyph_width *= factor;
+            glyph_height *= factor;
+        }
+        // (the `min` only guards against rounding error after shrinking)
+        let sdf_width = (glyph_width + padding).ceil()
+            .min(atlas_w as f32);
+        let sdf_height = (glyph_height + padding).ceil()
+            .min(atlas_h as f32);
+        let wrangled_glyph_width = sdf_width - padding;
+        let wrangled_glyph_height = sdf_height - padding;
+        let sdf_width_int = sdf_width as u32;
+        let sdf_height_int = sdf_height as u32;
         // font units -> sdf pixels
         let scale_x = wrangled_glyph_width / raw_glyph_width;
         let scale_y = wrangled_glyph_height / raw_glyph_height;
         let translate_x
-            = self.border_texels * 0.5
-            - bbox.x_min as f32 * scale_x;
+            = padding * 0.5
+            - bbox_x_min * scale_x;
         let translate_y
-            = self.border_texels * 0.5
-            - bbox.y_min as f32 * scale_y;
-        let border = self.border_texels as f64;
+            = padding * 0.5
+            - bbox_y_min * scale_y;
+        // Synthetic bold is done by shifting the distance field, below. The
+        // range has to be wide enough that the shift doesn't bring texels
+        // that were clamped to "fully outside" over the edge.
+        let embolden_texels = embolden * (scale_x + scale_y) * 0.5;
+        let range = params.distance_range as f64
+            + 2.0 * embolden_texels as f64;
+        // (skew first, then scale and translate)
         let transform = Affine::from_matrix_unchecked(Matrix::new(
-            scale_x as f64, 0.0, translate_x as f64,
+            scale_x as f64, (scale_x * skew) as f64, translate_x as f64,
             0.0, scale_y as f64, translate_y as f64,
             0.0, 0.0, 1.0,
         ));
         shape.transform(&transform);
 
-        let mut bitmap = RgbImage::new(sdf_width_int, sdf_height_int);
-
-        // Is this still right?
-        let colored_shape = Shape::edge_coloring_simple(shape, 0.3, 8).prepare(); // 8 is Admiral's favorite u64 apparently
+        // error correction wants the uncolored outline, for true distances
+        let plain_shape = match self.msdf.error_correction {
+            ErrorCorrection::Off => None,
+            _ => Some(shape.prepare()),
+        };
+        let sin_alpha = self.msdf.corner_angle_threshold().sin();
+        let colored_shape = match self.msdf.edge_coloring {
+            EdgeColoring::Simple
+                => Shape::edge_coloring_simple(shape, sin_alpha,
+                                               self.msdf.seed),
+        };
+        let corners: Vec<_> = colored_shape.contours.iter()
+            .flat_map(|contour| {
+                let segments = &contour.segments;
+                segments.iter().zip(segments.iter().cycle().skip(1))
+                    .filter(|(a, b)| a.color != b.color)
+                    .map(|(a, _)| a.segment.end())
+            }).collect();
+        let colored_shape = colored_shape.prepare();
 
         // render an SDF for it
-        fdsm::generate::generate_msdf(
-            &colored_shape,
-            border,
-            &mut bitmap,
-        );
-        fdsm::render::correct_sign_msdf(&mut bitmap, &colored_shape, FillRule::Nonzero);
-        {
-            use std::fs::File;
-            use std::io::Write;
-            let mut f = File::create("/tmp/thing.ppm").unwrap();
-            write!(f, "P6\n{} {} 255\n", sdf_width_int, sdf_height_int).unwrap();
-            f.write_all(&bitmap.as_flat_samples().as_slice()).unwrap();
-        }
-
-        let half_extra_width = (sdf_width - glyph_width)
-            / self.texels_per_em_x * 0.5;
-        let half_extra_height = (sdf_height - glyph_height)
-            / self.texels_per_em_y * 0.5;
-        let render_x_min = bbox.x_min as f32 / per_em - half_extra_width;
-        let render_y_min = bbox.y_min as f32 / per_em - half_extra_height;
-        let render_x_max = bbox.x_max as f32 / per_em + half_extra_width;
-        let render_y_max = bbox.y_max as f32 / per_em + half_extra_height;
-        Some((render_x_min, render_y_min,
-              render_x_max, render_y_max,
-              sdf_width_int, sdf_height_int,
-              bitmap))
+        let fill_rule = self.msdf.fill_rule.to_fdsm();
+        let (format, mut pixels) = if self.msdf.mtsdf {
+            let mut bitmap = RgbaImage::new(sdf_width_int, sdf_height_int);
+            fdsm::generate::generate_mtsdf(&colored_shape, range,
+                                           &mut bitmap);
+            fdsm::render::correct_sign_mtsdf(&mut bitmap, &colored_shape,
+                                             fill_rule);
+            (AtlasFormat::Mtsdf, bitmap.into_raw())
+        }
+        else {
+            let mut bitmap = RgbImage::new(sdf_width_int, sdf_height_int);
+            fdsm::generate::generate_msdf(&colored_shape, range,
+                                          &mut bitmap);
+            fdsm::render::correct_sign_msdf(&mut bitmap, &colored_shape,
+                                            fill_rule);
+            (AtlasFormat::Msdf, bitmap.into_raw())
+        };
+        if let Some(plain_shape) = plain_shape {
+            correct::correct_errors(&mut pixels, sdf_width_int,
+                                    format.bytes_per_texel(),
+                                    &plain_shape, &corners,
+                                    range, self.msdf.error_correction);
+        }
+        if embolden > 0.0 {
+            // Offsetting every channel of an MSDF by the same amount offsets
+            // the median, and therefore the outline, by that amount. (The
+            // true distance in an MTSDF's alpha channel moves along with
+            // it.)
+            let delta = (embolden_texels as f64 / range * 255.0)
+                .round() as u8;
+            for value in pixels.iter_mut() {
+                *value = value.saturating_add(delta);
+            }
+        }
+
+        // The glyph was stretched to fill the SDF minus its padding, so the
+        // padding covers this much of an em on each side.
+        let half_extra_width = padding * 0.5
+            / (scale_x * per_em);
+        let half_extra_height = padding * 0.5
+            / (scale_y * per_em);
+        let render_x_min = bbox_x_min / per_em - half_extra_width;
+        let render_y_min = bbox_y_min / per_em - half_extra_height;
+        let render_x_max = bbox_x_max / per_em + half_extra_width;
+        let render_y_max = bbox_y_max / per_em + half_extra_height;
+        Ok(RenderResult::Rendered(RenderedGlyph {
+            render_x_min, render_y_min,
+            render_x_max, render_y_max,
+            sdf_width_int, sdf_height_int,
+            format,
+            pixels,
+            metrics,
+            distance_range: range as f32,
+        }))
+    }
+    /// Works out a glyph's metrics, given the horizontal extent of whatever
+    /// it draws (in font units).
+    fn metrics(&self, glyph: GlyphId, x_min: f32, x_max: f32) -> GlyphMetrics {
+        let per_em = self.face().units_per_em() as f32;
+        let advance = self.face().glyph_hor_advance(glyph)
+            .unwrap_or(0) as f32;
+        GlyphMetrics {
+            advance: advance / per_em,
+            left_side_bearing: x_min / per_em,
+            right_side_bearing: (advance - x_max) / per_em,
+        }
+    }
+    /// Decodes a glyph's embedded PNG image (from `sbix`, `CBDT`, etc.) into
+    /// an RGBA bitmap, using the strike closest to our vertical texel
+    /// density. If the image is too big for an atlas, it is scaled down to
+    /// fit (unless `oversize` says not to); the render bounds still describe
+    /// the whole image.
+    ///
+    /// If there is no raster image either, the glyph is empty. If the image
+    /// is in a format we don't support, the glyph is treated as missing.
+    fn render_raster_glyph(&self, glyph: GlyphId, tier: f32,
+                           atlas_w: u32, atlas_h: u32,
+                           oversize: OversizeGlyphs)
+        -> Result<RenderResult, TooLarge> {
+        let ppem = (self.params.texels_per_em_y * tier).round()
+            .clamp(1.0, u16::MAX as f32);
+        let raster = self.face().glyph_raster_image(glyph, ppem as u16);
+        let raster = match raster {
+            Some(x) => x,
+            None => {
+                let lsb = self.face().glyph_hor_side_bearing(glyph)
+                    .unwrap_or(0);
+                return Ok(RenderResult::Empty(self.metrics(glyph,
+                                                           lsb as f32,
+                                                           lsb as f32)))
+            },
+        };
+        if raster.format != RasterImageFormat::PNG {
+            warn!("psilo-text only supports PNG raster glyphs, but glyph {} \
+                   is a {:?}", glyph.0, raster.format);
+            return Ok(RenderResult::Missing);
+        }
+        let mut image = match image::load_from_memory_with_format(
+            raster.data, image::ImageFormat::Png) {
+            Ok(x) => x.into_rgba8(),
+            Err(x) => {
+                warn!("Unable to decode the PNG for glyph {}: {}",
+                      glyph.0, x);
+                return Ok(RenderResult::Missing);
+            },
+        };
+        if image.width() == 0 || image.height() == 0 {
+            return Ok(RenderResult::Missing)
+        }
+        // PNGs are stored top to bottom, our atlases go bottom to top.
+        image::imageops::flip_vertical_in_place(&mut image);
+        if image.width() > atlas_w || image.height() > atlas_h {
+            if oversize == OversizeGlyphs::Reject
+            || atlas_w == 0 || atlas_h == 0 {
+                return Err(TooLarge {
+                    width: image.width(), height: image.height(),
+                });
+            }
+            let scale = (atlas_w as f32 / image.width() as f32)
+                .min(atlas_h as f32 / image.height() as f32);
+            let w = ((image.width() as f32 * scale) as u32).clamp(1, atlas_w);
+            let h = ((image.height() as f32 * scale) as u32).clamp(1, atlas_h);
+            image = image::imageops::resize(&image, w, h,
+                                            FilterType::Triangle);
+        }
+        // The raster's offset and size are in pixels at its own ppem.
+        let per_em = raster.pixels_per_em.max(1) as f32;
+        let render_x_min = raster.x as f32 / per_em;
+        let render_y_min = raster.y as f32 / per_em;
+        let render_x_max = (raster.x as f32 + raster.width as f32) / per_em;
+        let render_y_max = (raster.y as f32 + raster.height as f32) / per_em;
+        let units_per_em = self.face().units_per_em() as f32;
+        let metrics = self.metrics(glyph, render_x_min * units_per_em,
+                                   render_x_max * units_per_em);
+        Ok(RenderResult::Rendered(RenderedGlyph {
+            render_x_min, render_y_min,
+            render_x_max, render_y_max,
+            sdf_width_int: image.width(), sdf_height_int: image.height(),
+            format: AtlasFormat::Bitmap,
+            pixels: image.into_raw(),
+            metrics,
+            distance_range: 0.0,
+        }))
     }
 }
 
@@ -252,37 +1199,80 @@ enum GlyphStateInCache<AtlasID: Copy, AtlasCoords: Copy> {
     Null,
     #[cfg(feature="bg-render")]
     Pending,
+    /// Present in the font, but with nothing to put in an atlas.
+    Empty(GlyphMetrics),
     Present(GlyphState<AtlasID, AtlasCoords>),
 }
 
 impl<AtlasID: Copy, AtlasCoords: Copy> GlyphStateInCache<AtlasID, AtlasCoords> {
     #[cfg(feature="bg-render")]
     pub fn is_pending(&self) -> bool {
-        match self {
-            GlyphStateInCache::Pending => true,
-            _ => false,
-        }
+        matches!(self, GlyphStateInCache::Pending)
     }
 }
 
+/// Face index, glyph ID, atlas group, the settings it was rendered with, and
+/// its density tier (as the bits of an `f32`).
+type GlyphKey = (usize, u16, usize, MsdfKey, u32);
+
+/// A face's glyphs being rendered again with new render parameters. They're
+/// swapped in all at once, when they're all done.
+struct Rerender<AtlasID: Copy, AtlasCoords: Copy> {
+    /// Glyphs (and their density tiers) that haven't been started yet.
+    todo: Vec<(u16, f32)>,
+    /// Glyphs being rendered in the background, and the bits of their
+    /// density tiers.
+    in_flight: HashSet<(u16, u32)>,
+    /// Glyphs that are done, and already in an atlas, waiting for the rest.
+    staged: Vec<(GlyphKey, GlyphStateInCache<AtlasID, AtlasCoords>)>,
+    /// Glyphs (and the bits of their density tiers) that were first asked
+    /// for after the rerender started. They're rendered along with the rest,
+    /// and held back until the swap, so they don't show up early with the
+    /// new parameters.
+    held: HashSet<(u16, u32)>,
+}
+
 pub struct TextHandler<AtlasID: Copy, AtlasCoords: Copy> {
-    faces: Vec<FaceState>,
+    /// Keyed by face index. Indices are handed out in order, and never
+    /// reused, so that removing a face doesn't disturb the others.
+    faces: HashMap<usize, FaceState>,
+    next_face: usize,
+    /// Keyed by face index.
+    rerenders: HashMap<usize, Rerender<AtlasID, AtlasCoords>>,
     atlases: Vec<AtlasState<AtlasID>>,
-    glyphs: HashMap<(usize, u16), GlyphStateInCache<AtlasID, AtlasCoords>>,
+    glyphs: HashMap<GlyphKey, GlyphStateInCache<AtlasID, AtlasCoords>>,
     #[cfg(feature="bg-render")]
     bg: bg::Renderer,
     #[cfg(feature="bg-render")]
     render_in_bg: bool,
+    oversize_glyphs: OversizeGlyphs,
+    atlas_options: AtlasOptions,
+    render_stats: RenderStats,
+    /// How many glyphs to render again per `get_glyph`, in the foreground.
+    rerender_batch: usize,
+}
+
+impl<AtlasID: Copy, AtlasCoords: Copy> Default
+for TextHandler<AtlasID, AtlasCoords> {
+    fn default() -> TextHandler<AtlasID, AtlasCoords> {
+        TextHandler::new()
+    }
 }
 
 impl<AtlasID: Copy, AtlasCoords: Copy> TextHandler<AtlasID, AtlasCoords> {
     pub fn new() -> TextHandler<AtlasID, AtlasCoords> {
         TextHandler {
-            faces: Vec::new(),
+            faces: HashMap::new(),
+            next_face: 0,
+            rerenders: HashMap::new(),
             atlases: Vec::new(),
             glyphs: HashMap::new(),
             #[cfg(feature="bg-render")] bg: bg::Renderer::new(),
             #[cfg(feature="bg-render")] render_in_bg: true,
+            oversize_glyphs: OversizeGlyphs::default(),
+            atlas_options: AtlasOptions::default(),
+            render_stats: RenderStats::default(),
+            rerender_batch: 8,
         }
     }
     /// Set whether new glyphs will be rendered in the background. When
@@ -303,65 +1293,816 @@ impl<AtlasID: Copy, AtlasCoords: Copy> TextHandler<AtlasID, AtlasCoords> {
     pub fn set_render_in_background(&mut self, nu: bool) {
         self.render_in_bg = nu;
     }
+    /// Set what happens to glyphs that are too big to fit in an atlas at
+    /// their face's texel density. See [`OversizeGlyphs`] for the options.
+    /// Default is to shrink them.
+    ///
+    /// This only affects glyphs that haven't been rendered yet.
+    ///
+    /// [`OversizeGlyphs`]: enum.OversizeGlyphs.html
+    pub fn set_oversize_glyphs(&mut self, nu: OversizeGlyphs) {
+        self.oversize_glyphs = nu;
+    }
+    /// Set how many glyphs are rendered again, in the foreground, each time
+    /// you call `get_glyph` while a face is being rendered again (see
+    /// [`set_render_params`](#method.set_render_params)). Lower values
+    /// spread the work over more frames; higher values get it over with
+    /// sooner. Default is 8, and anything less than 1 counts as 1.
+    ///
+    /// This doesn't affect glyphs rendered in the background, which are all
+    /// sent off at once.
+    pub fn set_foreground_rerender_batch(&mut self, nu: usize) {
+        self.rerender_batch = nu.max(1);
+    }
+    /// Set how atlases map onto textures. See [`AtlasMode`] for the options.
+    /// Default is a separate texture for each atlas.
+    ///
+    /// Set this before asking for any glyphs. Atlases that already exist
+    /// are left as they are, even if there are more of them than
+    /// `max_layers` allows.
+    ///
+    /// [`AtlasMode`]: enum.AtlasMode.html
+    pub fn set_atlas_mode(&mut self, nu: AtlasMode) {
+        self.atlas_options.mode = nu;
+    }
+    /// Set which packer new atlases will use. See [`Packing`] for the
+    /// options. Default is [`Packing::Skyline`].
+    ///
+    /// Atlases that already exist keep the packer they were made with.
+    ///
+    /// [`Packing`]: enum.Packing.html
+    /// [`Packing::Skyline`]: enum.Packing.html#variant.Skyline
+    pub fn set_packing(&mut self, nu: Packing) {
+        self.atlas_options.packing = nu;
+    }
+    /// Set how much space to leave around glyphs in new atlases, and what to
+    /// align them to. See [`AtlasSpacing`]. Default is no space at all, and
+    /// no alignment.
+    ///
+    /// Atlases that already exist keep the spacing they were made with.
+    ///
+    /// [`AtlasSpacing`]: struct.AtlasSpacing.html
+    pub fn set_atlas_spacing(&mut self, nu: AtlasSpacing) {
+        self.atlas_options.spacing = nu;
+    }
+    /// Returns how full each atlas is, in the order they were made. Useful
+    /// for comparing packers (see [`set_packing`](#method.set_packing)) on
+    /// your own glyph mix.
+    pub fn get_atlas_occupancy(&self) -> Vec<AtlasOccupancy<AtlasID>> {
+        self.atlases.iter().map(|state| {
+            let (width, height) = state.size();
+            AtlasOccupancy {
+                atlas: state.handle,
+                group: state.group,
+                format: state.format,
+                layer: state.layer,
+                width, height,
+                used_texels: state.used_texels,
+                glyphs: state.glyphs,
+                largest_free: state.largest_free(),
+            }
+        }).collect()
+    }
+    /// Returns how full each atlas is, along with how much rendering has
+    /// been done so far and how long it took. Glyphs that are still being
+    /// rendered in the background are counted once they've been picked up by
+    /// `get_glyph`.
+    pub fn stats(&self) -> TextStats<AtlasID> {
+        TextStats {
+            atlases: self.get_atlas_occupancy(),
+            rendering: self.render_stats,
+        }
+    }
+    /// Returns every glyph that's currently in an atlas, in no particular
+    /// order.
+    pub fn cached_glyphs(&self)
+        -> impl Iterator<Item=CachedGlyph<'_, AtlasID, AtlasCoords>> {
+        self.glyphs.iter().filter_map(|(&(face, glyph, group, _, tier),
+                                        state)| {
+            match state {
+                GlyphStateInCache::Present(state) => Some(CachedGlyph {
+                    face, glyph, group,
+                    tier: f32::from_bits(tier),
+                    atlas: state.atlas,
+                    coords: state.coords,
+                    info: &state.info,
+                }),
+                _ => None,
+            }
+        })
+    }
     /// - `border_texels`: The number of texels of extra padding to put around
     ///   each SDF in the atlas for this face. When in doubt, use 4.0. This is
     ///   also the effective range of the SDF, so values less than 2.0 are
-    ///   suicide!
+    ///   suicide! (Use [`add_face_with_params`](#method.add_face_with_params)
+    ///   if you want the padding and the range to differ.)
     /// - `texels_per_em_*`: The number of texels that a single em in the given
     ///   font should occupy in the atlas. This should be experimentally
     ///   determined per font. 64 is usually a good starting point. Thinner
-    ///   fonts will need higher values.
-    pub fn add_face(&mut self, face_data: Arc<Vec<u8>>, index: u32,
-                    border_texels: f32,
+    ///   fonts will need higher values. (Or use
+    ///   [`add_face_auto`](#method.add_face_auto) to have it worked out.)
+    ///
+    /// The face starts out with the default [`MsdfConfig`], which fills
+    /// outlines with the nonzero winding rule, as both TrueType and CFF
+    /// outlines are meant to be. Some converted and icon fonts were made for
+    /// even-odd filling instead, and render with filled-in holes this way.
+    /// Add those faces with
+    /// [`add_face_with_fill_rule`](#method.add_face_with_fill_rule), or
+    /// switch them later with [`set_msdf_config`](#method.set_msdf_config).
+    ///
+    /// [`MsdfConfig`]: struct.MsdfConfig.html
+    pub fn add_face(&mut self, face_data: impl Into<FontData>,
+                    index: u32, border_texels: f32,
                     texels_per_em_x: f32, texels_per_em_y: f32)
         -> Option<usize> {
-        let face = Face::from_slice(&face_data, index)?;
-        let face: Face<'static> = unsafe { transmute(face) };
+        self.add_face_with_params(face_data, index, &[],
+                                  RenderParams::from_border(border_texels,
+                                                            texels_per_em_x,
+                                                            texels_per_em_y))
+    }
+    /// As [`add_face`](#method.add_face), but mapping the font file at
+    /// `path` into memory for you, with
+    /// [`FontData::map_file`](struct.FontData.html#method.map_file), rather
+    /// than reading it onto the heap. To pick up changes to the font, write
+    /// a new file and use [`replace_face_data`](#method.replace_face_data).
+    ///
+    /// Returns `Err` if the file can't be mapped, and `Ok(None)` if it
+    /// doesn't contain a face at `index`.
+    ///
+    /// # Safety
+    ///
+    /// As with `FontData::map_file`: the file must not be changed or
+    /// truncated, by this process or any other, for as long as the face (or
+    /// any variant made from it) is alive. Replacing the file by writing a
+    /// new one and renaming it over the old one is fine.
+    pub unsafe fn add_face_from_path<P: AsRef<std::path::Path>>
+        (&mut self, path: P, index: u32, border_texels: f32,
+         texels_per_em_x: f32, texels_per_em_y: f32)
+        -> std::io::Result<Option<usize>> {
+        let face_data = FontData::map_file(path)?;
+        Ok(self.add_face(face_data, index, border_texels,
+                         texels_per_em_x, texels_per_em_y))
+    }
+    /// Adds every face in the given font data (see
+    /// [`FaceInfo::list`](struct.FaceInfo.html#method.list)), all with the
+    /// same parameters, as with [`add_face`](#method.add_face). The faces
+    /// share the data, rather than each getting a copy.
+    ///
+    /// Returns a description of each face, along with its face index, in
+    /// the order they appear in the data. Faces that can't be parsed are
+    /// left out.
+    pub fn add_all_faces(&mut self, face_data: impl Into<FontData>,
+                         border_texels: f32,
+                         texels_per_em_x: f32, texels_per_em_y: f32)
+        -> Vec<(FaceInfo, usize)> {
+        let face_data = face_data.into();
+        FaceInfo::list(face_data.bytes()).into_iter().filter_map(|info| {
+            let face = self.add_face(face_data.clone(), info.index,
+                                     border_texels,
+                                     texels_per_em_x, texels_per_em_y)?;
+            Some((info, face))
+        }).collect()
+    }
+    /// As [`add_face`](#method.add_face), but working out the texel density
+    /// from the face's outlines, so that its thinnest strokes are at least
+    /// `min_stroke_texels` across. See
+    /// [`RenderParams::estimate_texels_per_em`].
+    ///
+    /// If the face doesn't have the glyphs that are measured, it gets 64
+    /// texels per em, with a warning.
+    ///
+    /// [`RenderParams::estimate_texels_per_em`]: struct.RenderParams.html#method.estimate_texels_per_em
+    pub fn add_face_auto(&mut self, face_data: impl Into<FontData>,
+                         index: u32, border_texels: f32,
+                         min_stroke_texels: f32)
+        -> Option<usize> {
+        let face_data = face_data.into();
+        let face = ttf_parser::Face::parse(face_data.bytes(), index).ok()?;
+        let params = RenderParams::auto(&face, border_texels,
+                                        min_stroke_texels)
+            .unwrap_or_else(|| {
+                warn!("Face {} of the given font has no glyphs to measure \
+                       its strokes by, using 64 texels per em", index);
+                RenderParams::from_border(border_texels, 64.0, 64.0)
+            });
+        self.add_face_with_params(face_data, index, &[], params)
+    }
+    /// As [`add_face`](#method.add_face), but filling the face's outlines
+    /// with the given fill rule, instead of nonzero winding.
+    pub fn add_face_with_fill_rule(&mut self,
+                                   face_data: impl Into<FontData>,
+                                   index: u32, fill_rule: FillRule,
+                                   border_texels: f32, texels_per_em_x: f32,
+                                   texels_per_em_y: f32)
+        -> Option<usize> {
+        let face = self.add_face(face_data, index, border_texels,
+                                 texels_per_em_x, texels_per_em_y)?;
+        let msdf = MsdfConfig { fill_rule, ..self.get_msdf_config(face)? };
+        self.set_msdf_config(face, msdf)?;
+        Some(face)
+    }
+    /// As [`add_face`](#method.add_face), but for a specific instance of a
+    /// variable font. `variations` gives coordinates for the axes you care
+    /// about (weight, width, optical size...); any axis not mentioned stays at
+    /// its default value. Glyph outlines will be loaded with these variations
+    /// in effect.
+    ///
+    /// You can add the same font data several times with different
+    /// variations. Each instance gets its own face index, and therefore its
+    /// own glyphs in the cache. If `face_data` is the same `Arc` (and `index`
+    /// is the same) as a face that was already added, the already-parsed face
+    /// is reused instead of parsing the data again.
+    ///
+    /// Axes that the face doesn't have are ignored, with a warning.
+    pub fn add_face_with_variations(&mut self,
+                                    face_data: impl Into<FontData>,
+                                    index: u32, variations: &[Variation],
+                                    border_texels: f32,
+                                    texels_per_em_x: f32, texels_per_em_y: f32)
+        -> Option<usize> {
+        self.add_face_with_params(face_data, index, variations,
+                                  RenderParams::from_border(border_texels,
+                                                            texels_per_em_x,
+                                                            texels_per_em_y))
+    }
+    /// As [`add_face_with_variations`](#method.add_face_with_variations),
+    /// but with separate control over the distance range and the padding.
+    /// See [`RenderParams`](struct.RenderParams.html).
+    pub fn add_face_with_params(&mut self,
+                                face_data: impl Into<FontData>,
+                                index: u32, variations: &[Variation],
+                                params: RenderParams)
+        -> Option<usize> {
+        let face_data = face_data.into();
+        let existing = self.faces.values().find(|x| {
+            x.index == index && x.owned.data().ptr_eq(&face_data)
+        });
+        let mut owned = match existing {
+            Some(existing) => {
+                let mut owned = existing.owned.clone();
+                // The existing face may be a different instance. Go back to
+                // the default instance before applying our own variations.
+                owned.reset_variations();
+                owned
+            },
+            None => OwnedFace::parse(face_data, index)?,
+        };
+        for variation in variations {
+            if owned.set_variation(variation.tag, variation.value).is_none() {
+                warn!("Face {} of the given font has no {} axis, ignoring \
+                       that variation", index, variation.tag);
+            }
+        }
+        Some(self.push_face(FaceState {
+            owned, index,
+            variations: variations.to_vec(),
+            revision: 0,
+            params,
+            tiers: vec![1.0],
+            synthetic: SyntheticStyle::default(),
+            msdf: MsdfConfig::default(),
+            atlas_group: 0,
+        }))
+    }
+    /// Adds a synthetically styled variant of an existing face, for fonts
+    /// that don't ship with the style you need. The new face shares the
+    /// font data, variations, and render parameters of `base`, but gets its
+    /// own face index, and therefore its own glyphs in the cache.
+    ///
+    /// Synthetic styling only affects how glyphs are drawn. Your shaping
+    /// engine will still give you the advances of the original face, so you
+    /// may want to add a little extra spacing for a synthetic bold.
+    ///
+    /// Returns `None` if `base` is not a valid face index.
+    pub fn add_face_variant(&mut self, base: usize, synthetic: SyntheticStyle)
+        -> Option<usize> {
+        let mut face_state = self.faces.get(&base)?.clone();
+        face_state.synthetic = synthetic;
+        Some(self.push_face(face_state))
+    }
+    fn push_face(&mut self, face_state: FaceState) -> usize {
+        let face = self.next_face;
+        self.next_face += 1;
+        #[cfg(feature = "bg-render")] {
+            self.bg.add_face(face, face_state.clone());
+        }
+        self.faces.insert(face, face_state);
+        face
+    }
+    /// Removes a face, when you're done with it for good. Its glyphs are
+    /// dropped from the cache, and the space they took up in their atlases
+    /// can be used by other glyphs. (Whether it actually is depends on the
+    /// packer; see [`AtlasPacker::free`].) Their texels are left as they
+    /// are until something else is put there. Any of its glyphs that are
+    /// waiting to be rendered in the background are cancelled.
+    ///
+    /// The other faces keep their indices, and `face`'s index is never
+    /// handed out again. Using it after this is like using any other invalid
+    /// face index. If other faces were added from the same font data, they
+    /// keep it alive; otherwise, it's dropped.
+    ///
+    /// Returns `None` if `face` is not a valid face index.
+    ///
+    /// [`AtlasPacker::free`]: trait.AtlasPacker.html#method.free
+    pub fn remove_face(&mut self, face: usize) -> Option<()> {
+        self.faces.remove(&face)?;
+        #[cfg(feature = "bg-render")] {
+            self.bg.cancel_renders(face);
+            self.bg.remove_face(face);
+        }
+        self.forget_glyphs(face);
+        Some(())
+    }
+    /// Replaces the font data of a face, for instance because the font file
+    /// has changed on disk. The new data is parsed as face number `index`
+    /// (the same index the face was added with), and gets the same
+    /// variations the face was added with. Everything else about the face,
+    /// including its index, stays the same.
+    ///
+    /// All of the face's glyphs are dropped from the cache, freeing their
+    /// space in the atlases (see [`remove_face`](#method.remove_face)), and
+    /// will be rendered from the new outlines the next time they're asked
+    /// for. Glyphs that were being rendered from the old data in the
+    /// background are thrown away.
+    ///
+    /// Other faces that were added from the old data, including synthetic
+    /// variants of this one, keep using it. Replace their data too, if you
+    /// want them to change.
+    ///
+    /// Returns `None`, and leaves the face alone, if `face` is not a valid
+    /// face index, or if the new data doesn't contain a face at the right
+    /// index.
+    pub fn replace_face_data(&mut self, face: usize,
+                             face_data: impl Into<FontData>)
+        -> Option<()> {
+        let face_data = face_data.into();
+        let face_state = self.faces.get_mut(&face)?;
+        let mut owned = OwnedFace::parse(face_data, face_state.index)?;
+        for variation in face_state.variations.iter() {
+            if owned.set_variation(variation.tag, variation.value).is_none() {
+                warn!("The new data for face {} has no {} axis, ignoring \
+                       that variation", face, variation.tag);
+            }
+        }
+        face_state.owned = owned;
+        self.outlines_changed(face);
+        Some(())
+    }
+    /// Changes the variations of a face added with
+    /// [`add_face_with_variations`](#method.add_face_with_variations) (or of
+    /// any other face of a variable font). As there, any axis not mentioned
+    /// goes back to its default value, and axes that the face doesn't have
+    /// are ignored, with a warning. The new variations are also the ones
+    /// used if the face's data is replaced.
+    ///
+    /// The face's glyphs are dropped from the cache, as with
+    /// [`replace_face_data`](#method.replace_face_data). Other faces added
+    /// from the same font data, including synthetic variants of this one,
+    /// keep their own variations.
+    ///
+    /// Returns `None` if `face` is not a valid face index.
+    pub fn set_face_variations(&mut self, face: usize,
+                               variations: &[Variation])
+        -> Option<()> {
+        let face_state = self.faces.get_mut(&face)?;
+        face_state.owned.reset_variations();
+        for variation in variations {
+            if face_state.owned.set_variation(variation.tag, variation.value)
+                .is_none() {
+                warn!("Face {} has no {} axis, ignoring that variation",
+                      face, variation.tag);
+            }
+        }
+        face_state.variations = variations.to_vec();
+        self.outlines_changed(face);
+        Some(())
+    }
+    /// Called when a face's outlines have changed out from under its glyphs.
+    /// Throws away everything rendered from the old ones, here and in the
+    /// background.
+    fn outlines_changed(&mut self, face: usize) {
+        let face_state = self.faces.get_mut(&face)
+            .expect("Face index out of range");
+        face_state.revision += 1;
+        #[cfg(feature = "bg-render")] {
+            self.bg.cancel_renders(face);
+            self.bg.replace_face(face, face_state.clone());
+        }
+        self.forget_glyphs(face);
+    }
+    /// Drops all of a face's glyphs from the cache, freeing their space in
+    /// the atlases. This includes any that are being rendered again.
+    fn forget_glyphs(&mut self, face: usize) {
+        let atlases = &mut self.atlases;
+        if let Some(rerender) = self.rerenders.remove(&face) {
+            for (_, state) in rerender.staged {
+                if let GlyphStateInCache::Present(state) = state {
+                    atlases[state.atlas_index].free(state.info.atlas_rect);
+                }
+            }
+        }
+        self.glyphs.retain(|&(glyph_face, ..), state| {
+            if glyph_face != face { return true }
+            if let GlyphStateInCache::Present(state) = state {
+                atlases[state.atlas_index].free(state.info.atlas_rect);
+            }
+            false
+        });
+    }
+    /// Change the MSDF generation settings for a face. Glyphs rendered from
+    /// now on will use the new settings. Glyphs already rendered with other
+    /// settings stay in their atlases, in case you switch back.
+    ///
+    /// Returns `None` if `face` is not a valid face index.
+    pub fn set_msdf_config(&mut self, face: usize, config: MsdfConfig)
+        -> Option<()> {
+        let face_state = self.faces.get_mut(&face)?;
+        face_state.msdf = config;
+        #[cfg(feature = "bg-render")] {
+            self.bg.replace_face(face, face_state.clone());
+        }
+        Some(())
+    }
+    /// Put a face's glyphs into a different group of atlases. Your
+    /// `AtlasHandler` is told the group when asked for a new atlas or the
+    /// atlas size, so different groups can have different sizes and
+    /// textures: a big atlas for a CJK face, a small one for a HUD font, and
+    /// so on. Faces start out in group 0.
+    ///
+    /// Glyphs rendered from now on will go into the new group. Glyphs
+    /// already in the old group's atlases stay there, in case you switch
+    /// back.
+    ///
+    /// Returns `None` if `face` is not a valid face index.
+    pub fn set_atlas_group(&mut self, face: usize, group: usize)
+        -> Option<()> {
+        let face_state = self.faces.get_mut(&face)?;
+        face_state.atlas_group = group;
         #[cfg(feature = "bg-render")] {
-            self.bg.add_face(face_data.clone(), face.clone(),
-                             border_texels, texels_per_em_x, texels_per_em_y);
+            self.bg.replace_face(face, face_state.clone());
         }
-        self.faces.push(FaceState { _face_data: face_data, face, border_texels,
-                                     texels_per_em_x, texels_per_em_y });
-        Some(self.faces.len()-1)
+        Some(())
     }
-    pub fn get_face(&self, i: usize) -> Option<&Face> {
-        // We need to massage the lifetime here. We have told the compiler that
-        // this Face has `'static` lifetime, but in truth it is only valid as
-        // long as we are. `transmute` will do the appropriate massaging.
-        unsafe { transmute(self.faces.get(i).map(|x| &x.face)) }
+    /// Returns the atlas group of a face, or `None` if `face` is not a valid
+    /// face index.
+    pub fn get_atlas_group(&self, face: usize) -> Option<usize> {
+        self.faces.get(&face).map(|x| x.atlas_group)
     }
-    pub fn get_face_mut(&mut self, i: usize) -> Option<&mut Face> {
-        unsafe { transmute(self.faces.get_mut(i).map(|x| &mut x.face)) }
+    /// Change the texel density, distance range and padding of a face, for
+    /// instance when the UI scale or a quality setting changes. Glyphs
+    /// rendered from now on will use the new parameters.
+    ///
+    /// Glyphs that are already in an atlas are rendered again with the new
+    /// parameters, in the background if background rendering is enabled.
+    /// Until they're *all* done, `get_glyph` keeps returning the old ones,
+    /// so that text is never drawn with a mix of both; then they're swapped
+    /// in at once, and the old ones' space in the atlases is freed (see
+    /// [`remove_face`](#method.remove_face)). Glyphs that are first asked
+    /// for in the meantime are rendered along with them, and `get_glyph`
+    /// returns `Ok(None)` for them, as if they were being rendered in the
+    /// background, until the swap.
+    ///
+    /// Rendering only progresses while you call `get_glyph` (for any face),
+    /// and you can check on it with
+    /// [`is_rerendering`](#method.is_rerendering). In the foreground, only a
+    /// few glyphs are rendered per call (see
+    /// [`set_foreground_rerender_batch`][1]), so that a face with many
+    /// glyphs doesn't stall a single frame. Changing the parameters again
+    /// before it's done starts it over.
+    ///
+    /// Glyphs that were rendered with other settings (another atlas group
+    /// or `MsdfConfig`) are dropped rather than rendered again. Glyphs in
+    /// every density tier are rendered again, since the tiers are relative
+    /// to these parameters.
+    ///
+    /// Returns `None` if `face` is not a valid face index.
+    ///
+    /// [1]: #method.set_foreground_rerender_batch
+    pub fn set_render_params(&mut self, face: usize, params: RenderParams)
+        -> Option<()> {
+        let face_state = self.faces.get_mut(&face)?;
+        face_state.params = params;
+        // Anything still being rendered with the old parameters is no use.
+        face_state.revision += 1;
+        #[cfg(feature = "bg-render")] {
+            self.bg.cancel_renders(face);
+            self.bg.replace_face(face, face_state.clone());
+        }
+        let current = |glyph, tier| face_state.glyph_key(face, glyph, tier);
+        let mut rerender = Rerender {
+            todo: vec![], in_flight: HashSet::new(), staged: vec![],
+            held: HashSet::new(),
+        };
+        let mut dropped = vec![];
+        for (&key, state) in self.glyphs.iter() {
+            if key.0 != face { continue }
+            match state {
+                GlyphStateInCache::Present(_)
+                if key == current(key.1, f32::from_bits(key.4)) => {
+                    rerender.todo.push((key.1, f32::from_bits(key.4)));
+                },
+                // Pending glyphs are requested again by the next `get_glyph`
+                // for them.
+                #[cfg(feature="bg-render")]
+                GlyphStateInCache::Pending => dropped.push(key),
+                GlyphStateInCache::Present(_) => dropped.push(key),
+                // Neither of these depends on the parameters.
+                GlyphStateInCache::Null | GlyphStateInCache::Empty(_) => (),
+            }
+        }
+        if let Some(old) = self.rerenders.insert(face, rerender) {
+            for (_, state) in old.staged {
+                if let GlyphStateInCache::Present(state) = state {
+                    self.atlases[state.atlas_index]
+                        .free(state.info.atlas_rect);
+                }
+            }
+        }
+        for key in dropped {
+            if let Some(GlyphStateInCache::Present(state))
+                = self.glyphs.remove(&key) {
+                self.atlases[state.atlas_index].free(state.info.atlas_rect);
+            }
+        }
+        Some(())
+    }
+    /// Returns true if the given face's glyphs are being rendered again,
+    /// after a call to [`set_render_params`](#method.set_render_params).
+    pub fn is_rerendering(&self, face: usize) -> bool {
+        self.rerenders.contains_key(&face)
     }
+    /// Returns the render parameters of a face, or `None` if `face` is not a
+    /// valid face index.
+    pub fn get_render_params(&self, face: usize) -> Option<RenderParams> {
+        self.faces.get(&face).map(|x| x.params)
+    }
+    /// Returns the MSDF generation settings for a face, or `None` if `face`
+    /// is not a valid face index.
+    pub fn get_msdf_config(&self, face: usize) -> Option<MsdfConfig> {
+        self.faces.get(&face).map(|x| x.msdf)
+    }
+    /// Gives a face extra density tiers, for text that's drawn at very
+    /// different sizes. One texel density either wastes atlas space on
+    /// small text or looks blobby on huge text; with tiers, each size of
+    /// text gets glyphs rendered at a density that suits it.
+    ///
+    /// Each tier is a multiple of the face's texel densities (see
+    /// [`RenderParams`]), so `&[0.5, 2.0, 4.0]` gives you tiers at half,
+    /// twice and four times the usual density. The usual density (1.0) is
+    /// always a tier, and is what `get_glyph` uses. The distance range and
+    /// padding stay the same number of texels in every tier.
+    ///
+    /// Use [`get_glyph_for_size`](#method.get_glyph_for_size) to get a
+    /// glyph in whichever tier suits the size you're drawing it at. Each
+    /// glyph is rendered in each tier the first time it's asked for there,
+    /// and cached separately.
+    ///
+    /// Tiers that aren't finite and positive are ignored. Glyphs already
+    /// rendered in tiers that are no longer in the list stay in their
+    /// atlases, in case you switch back.
+    ///
+    /// Returns `None` if `face` is not a valid face index.
+    ///
+    /// [`RenderParams`]: struct.RenderParams.html
+    pub fn set_density_tiers(&mut self, face: usize, tiers: &[f32])
+        -> Option<()> {
+        let face_state = self.faces.get_mut(&face)?;
+        let mut tiers: Vec<f32> = tiers.iter().copied()
+            .filter(|x| x.is_finite() && *x > 0.0)
+            .chain(std::iter::once(1.0))
+            .collect();
+        tiers.sort_by(f32::total_cmp);
+        tiers.dedup();
+        face_state.tiers = tiers;
+        #[cfg(feature = "bg-render")] {
+            self.bg.replace_face(face, face_state.clone());
+        }
+        Some(())
+    }
+    /// Returns the density tiers of a face, in ascending order, or `None` if
+    /// `face` is not a valid face index.
+    pub fn get_density_tiers(&self, face: usize) -> Option<&[f32]> {
+        self.faces.get(&face).map(|x| &x.tiers[..])
+    }
+    /// Returns the density tier that
+    /// [`get_glyph_for_size`](#method.get_glyph_for_size) would use for
+    /// text drawn at `pixels_per_em`: the lowest tier with at least one
+    /// texel per pixel (going by the vertical density), or the highest tier
+    /// if none has that many. Returns `None` if `face` is not a valid face
+    /// index.
+    pub fn tier_for_size(&self, face: usize, pixels_per_em: f32)
+        -> Option<f32> {
+        self.faces.get(&face).map(|x| x.tier_for_size(pixels_per_em))
+    }
+    /// The key under which the given glyph of the given face would currently
+    /// be cached, in the given density tier. `None` if there's no such face.
+    fn glyph_key(&self, face: usize, glyph: u16, tier: f32)
+        -> Option<GlyphKey> {
+        self.faces.get(&face).map(|x| x.glyph_key(face, glyph, tier))
+    }
+    /// Returns the parsed face, for shaping or for reading its tables. It
+    /// borrows from the font data we hold, so it can't outlive the borrow of
+    /// us.
+    ///
+    /// There's no way to change the face through this. To change its
+    /// variations, use
+    /// [`set_face_variations`](#method.set_face_variations), so that its
+    /// glyphs are rendered again.
+    pub fn get_face(&self, i: usize) -> Option<&Face<'_>> {
+        self.faces.get(&i).map(FaceState::face)
+    }
+    /// Returns true if the given glyph is a color glyph (i.e. it has `COLR`
+    /// layers), in which case you should draw it with
+    /// [`get_color_glyph`](#method.get_color_glyph) instead of `get_glyph`.
+    /// Returns false if `face` is not a valid face index.
+    pub fn is_color_glyph(&self, face: usize, glyph: u16) -> bool {
+        self.faces.get(&face).is_some_and(|x| {
+            colr::is_color_glyph(x.face(), GlyphId(glyph))
+        })
+    }
+    /// Decomposes a color glyph into its layers, making sure each layer is
+    /// rendered into an atlas as an ordinary glyph. Layers are returned
+    /// bottom-most first, with colors taken from the given `CPAL` palette.
+    /// (Palette 0 is the default; an out-of-range palette also gives you
+    /// palette 0.) Draw the layers as stacked quads, each tinted with its
+    /// color.
+    ///
+    /// Returns `Ok(None)` if the glyph isn't a color glyph (see
+    /// [`is_color_glyph`](#method.is_color_glyph)), or if any of its layers
+    /// are still being rendered in the background. Layers that have no shape
+    /// are left out. Returns `Err(Error::NoSuchFace)` if `face` is not a
+    /// valid face index.
+    pub fn get_color_glyph<A>(&mut self, face: usize, glyph: u16,
+                              palette: u16, handler: &mut A)
+        -> GlyphResult<Vec<ColorLayer<AtlasID, AtlasCoords>>, A::E>
+    where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+        let face_state = self.faces.get(&face)
+            .ok_or(Error::NoSuchFace { face })?;
+        let layers = match colr::color_glyph_layers(face_state.face(),
+                                                    GlyphId(glyph), palette) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let mut ret = Vec::with_capacity(layers.len());
+        let mut pending = false;
+        for (layer_glyph, color) in layers {
+            // Keep going even if something is pending, so that all the
+            // layers get dispatched at once.
+            match self.get_glyph(face, layer_glyph, handler)? {
+                Some((atlas, coords, _)) => {
+                    ret.push(ColorLayer { glyph: layer_glyph, color,
+                                          atlas, coords });
+                },
+                None => {
+                    pending = pending || self.is_pending(face, layer_glyph);
+                },
+            }
+        }
+        if pending { Ok(None) } else { Ok(Some(ret)) }
+    }
+    /// Returns the metrics of a glyph that has already been requested with
+    /// [`get_glyph`](#method.get_glyph), including glyphs that have nothing
+    /// to draw. Returns `None` if the glyph hasn't been requested yet, is
+    /// still being rendered in the background, or is missing from the font,
+    /// or if `face` is not a valid face index.
+    ///
+    /// So, if `get_glyph` returned `Ok(None)` and this returns `Some`, the
+    /// glyph is empty (like a space), and you should just advance the pen.
+    ///
+    /// Metrics are the same in every density tier, so a glyph requested with
+    /// [`get_glyph_for_size`](#method.get_glyph_for_size) counts too.
+    pub fn get_glyph_metrics(&self, face: usize, glyph: u16)
+        -> Option<GlyphMetrics> {
+        let face_state = self.faces.get(&face)?;
+        face_state.tiers.iter().find_map(|&tier| {
+            match self.glyphs.get(&face_state.glyph_key(face, glyph, tier))? {
+                GlyphStateInCache::Empty(metrics) => Some(*metrics),
+                GlyphStateInCache::Present(state) => Some(state.info.metrics),
+                _ => None,
+            }
+        })
+    }
+    /// Returns everything we know about a glyph that has already been put
+    /// into an atlas by [`get_glyph`](#method.get_glyph): where to draw it,
+    /// where it is in the atlas, its metrics, and its distance range. Returns
+    /// `None` for glyphs that aren't in an atlas (yet), and if `face` is not a
+    /// valid face index.
+    pub fn get_glyph_info(&self, face: usize, glyph: u16)
+        -> Option<&GlyphInfo> {
+        self.get_glyph_info_in_tier(face, glyph, 1.0)
+    }
+    /// As [`get_glyph_info`](#method.get_glyph_info), for a glyph gotten
+    /// with [`get_glyph_for_size`](#method.get_glyph_for_size). The render
+    /// bounds differ slightly between tiers, since the padding is a fixed
+    /// number of texels, so use these.
+    pub fn get_glyph_info_for_size(&self, face: usize, glyph: u16,
+                                   pixels_per_em: f32)
+        -> Option<&GlyphInfo> {
+        let tier = self.tier_for_size(face, pixels_per_em)?;
+        self.get_glyph_info_in_tier(face, glyph, tier)
+    }
+    fn get_glyph_info_in_tier(&self, face: usize, glyph: u16, tier: f32)
+        -> Option<&GlyphInfo> {
+        match self.glyphs.get(&self.glyph_key(face, glyph, tier)?)? {
+            GlyphStateInCache::Present(state) => Some(&state.info),
+            _ => None,
+        }
+    }
+    fn is_pending(&self, face: usize, glyph: u16) -> bool {
+        if self.rerenders.get(&face)
+            .is_some_and(|x| x.held.contains(&(glyph, 1.0f32.to_bits()))) {
+            return true
+        }
+        #[cfg(feature="bg-render")] {
+            self.glyph_key(face, glyph, 1.0).and_then(|x| self.glyphs.get(&x))
+                .is_some_and(|x| x.is_pending())
+        }
+        #[cfg(not(feature="bg-render"))] {
+            false
+        }
+    }
+    /// Returns `Ok(None)` if the glyph is missing from the font, or if it has
+    /// nothing to draw (like a space). Use
+    /// [`get_glyph_metrics`](#method.get_glyph_metrics) to tell the two
+    /// apart. Returns `Err(Error::NoSuchFace)` if `face` is not a valid face
+    /// index.
+    ///
     /// If the `bg-render` feature is enabled, this may render new glyphs in
     /// the background. The `bg-render` feature is *disabled* by default.
+    ///
+    /// Errors that occur while putting a background-rendered glyph into an
+    /// atlas are logged, rather than returned, and that glyph is treated as
+    /// missing from then on.
     pub fn get_glyph<A>(&mut self, face: usize, glyph: u16, handler: &mut A)
-        -> Result<Option<(AtlasID, AtlasCoords)>, A::E>
+        -> GlyphResult<(AtlasID, AtlasCoords, AtlasFormat), A::E>
+    where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+        self.get_glyph_in_tier(face, glyph, 1.0, handler)
+    }
+    /// As [`get_glyph`](#method.get_glyph), but in the density tier that
+    /// suits text drawn at `pixels_per_em` (see
+    /// [`tier_for_size`](#method.tier_for_size) and
+    /// [`set_density_tiers`](#method.set_density_tiers)). Get the glyph's
+    /// render bounds from
+    /// [`get_glyph_info_for_size`](#method.get_glyph_info_for_size).
+    ///
+    /// Faces start out with only one tier, in which case this is the same as
+    /// `get_glyph`.
+    pub fn get_glyph_for_size<A>(&mut self, face: usize, glyph: u16,
+                                 pixels_per_em: f32, handler: &mut A)
+        -> GlyphResult<(AtlasID, AtlasCoords, AtlasFormat), A::E>
     where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+        let tier = self.tier_for_size(face, pixels_per_em)
+            .ok_or(Error::NoSuchFace { face })?;
+        self.get_glyph_in_tier(face, glyph, tier, handler)
+    }
+    fn get_glyph_in_tier<A>(&mut self, face: usize, glyph: u16, tier: f32,
+                            handler: &mut A)
+        -> GlyphResult<(AtlasID, AtlasCoords, AtlasFormat), A::E>
+    where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+        let mut resized = vec![];
         #[cfg(feature="bg-render")]
-        while let Some((face, glyph, render_x_min, render_y_min,
-                        render_x_max, render_y_max, sdf_width_int,
-                        sdf_height_int, bitmap))
+        while let Some(bg::BgRendered { key, result: rendered, time,
+                                        revision })
             = self.bg.next_rendered_glyph() {
                 use std::collections::hash_map::Entry;
-                match self.glyphs.entry((face, glyph)) {
+                let (face, glyph, group, _, tier_bits) = key;
+                self.render_stats.count(&rendered, time, true);
+                // (It may have been rendered before its face was removed, or
+                // its font data replaced.)
+                if self.faces.get(&face).map(|x| x.revision) != Some(revision) {
+                    continue
+                }
+                if let Some(rerender) = self.rerenders.get_mut(&face)
+                    .filter(|x| x.in_flight.contains(&(glyph, tier_bits))) {
+                    rerender.in_flight.remove(&(glyph, tier_bits));
+                    if let Some(state) = cache_rerendered(&mut self.atlases,
+                                                          handler, key,
+                                                          self.atlas_options,
+                                                          &mut resized,
+                                                          rendered) {
+                        rerender.staged.push((key, state));
+                    }
+                    continue
+                }
+                match self.glyphs.entry(key) {
                     Entry::Vacant(_) => {
                         warn!("Glyph {} of face {}: rendered without us \
                                asking for it?", glyph, face);
                     },
-                    Entry::Occupied(mut ent) => {
-                        if ent.get().is_pending() {
-                            let (atlas_w, atlas_h) = handler.get_atlas_size();
-                            let res = put_into_atlas(&mut self.atlases,
-                                                     handler, atlas_w, atlas_h,
-                                                     render_x_min, render_y_min,
-                                                     render_x_max, render_y_max,
-                                                     sdf_width_int, sdf_height_int,
-                                                     bitmap);
+                    Entry::Occupied(ent) if !ent.get().is_pending() => {
+                        warn!("Glyph {} of face {}: rendered more than \
+                               once?", glyph, face);
+                    },
+                    Entry::Occupied(mut ent) => match rendered {
+                        Ok(rendered) => {
+                            let res = cache_render_result(&mut self.atlases,
+                                                          handler, group,
+                                                          self.atlas_options,
+                                                          &mut resized,
+                                                          rendered);
                             match res {
                                 Ok(res) => {
-                                    ent.insert(GlyphStateInCache::Present(res));
+                                    ent.insert(res);
                                 },
                                 Err(_) => {
                                     log::error!("Error inserting \
@@ -370,24 +2111,45 @@ impl<AtlasID: Copy, AtlasCoords: Copy> TextHandler<AtlasID, AtlasCoords> {
                                     ent.insert(GlyphStateInCache::Null);
                                 }
                             }
-                        }
-                        else {
-                            warn!("Glyph {} of face {}: rendered more than \
-                                   once?", glyph, face);
-                        }
+                        },
+                        Err(TooLarge { width, height }) => {
+                            log::error!("Glyph {} of face {}: would be {}x{} \
+                                         texels, too large for an atlas",
+                                        glyph, face, width, height);
+                            ent.insert(GlyphStateInCache::Null);
+                        },
                     },
                 }
             }
+        self.advance_rerenders(handler, &mut resized);
         let mut err = None;
-        let ret = self.glyphs.entry((face, glyph)).or_insert_with(|| {
+        let key = self.glyph_key(face, glyph, tier)
+            .ok_or(Error::NoSuchFace { face })?;
+        let group = key.2;
+        // A new glyph of a face that's being rendered again waits for the
+        // rest.
+        if let Some(rerender) = self.rerenders.get_mut(&face)
+            .filter(|_| !self.glyphs.contains_key(&key)) {
+            if rerender.held.insert((glyph, tier.to_bits())) {
+                rerender.todo.push((glyph, tier));
+            }
+            if !resized.is_empty() {
+                self.rederive_coords(handler, &resized)?;
+            }
+            return Ok(None)
+        }
+        self.glyphs.entry(key).or_insert_with(|| {
             let render_in_bg;
-            let (atlas_w, atlas_h) = handler.get_atlas_size();
+            let (atlas_w, atlas_h) = max_atlas_size(handler, group);
+            let (atlas_w, atlas_h) = self.atlas_options.spacing
+                .usable_size(atlas_w, atlas_h);
+            let oversize = self.oversize_glyphs;
             #[cfg(feature="bg-render")] { render_in_bg = self.render_in_bg; }
             #[cfg(not(feature="bg-render"))] { render_in_bg = false; }
             if render_in_bg {
                 #[cfg(feature="bg-render")] {
-                    self.bg.render_glyph(face, GlyphId(glyph),
-                                         atlas_w, atlas_h);
+                    self.bg.render_glyph(face, GlyphId(glyph), tier,
+                                         atlas_w, atlas_h, oversize);
                     GlyphStateInCache::Pending
                 }
                 #[cfg(not(feature="bg-render"))] {
@@ -396,23 +2158,28 @@ impl<AtlasID: Copy, AtlasCoords: Copy> TextHandler<AtlasID, AtlasCoords> {
             }
             else {
                 // get the glyph from the font
-                let face_state = self.faces.get_mut(face)
+                let face_state = self.faces.get_mut(&face)
                     .expect("Face index out of range");
-                let (render_x_min, render_y_min, render_x_max, render_y_max,
-                     sdf_width_int, sdf_height_int, bitmap)
-                    = match face_state.render_glyph(GlyphId(glyph),
-                                                    atlas_w, atlas_h) {
-                        None => return GlyphStateInCache::Null,
-                        Some(x) => x,
-                    };
-                let res = put_into_atlas(&mut self.atlases,
-                                         handler, atlas_w, atlas_h,
-                                         render_x_min, render_y_min,
-                                         render_x_max, render_y_max,
-                                         sdf_width_int, sdf_height_int,
-                                         bitmap);
+                let start = Instant::now();
+                let rendered = face_state.render_glyph(GlyphId(glyph), tier,
+                                                       atlas_w, atlas_h,
+                                                       oversize);
+                self.render_stats.count(&rendered, start.elapsed(), false);
+                let rendered = match rendered {
+                    Ok(x) => x,
+                    Err(TooLarge { width, height }) => {
+                        err = Some(Error::GlyphTooLarge {
+                            face, glyph, width, height,
+                        });
+                        return GlyphStateInCache::Null
+                    },
+                };
+                let res = cache_render_result(&mut self.atlases,
+                                              handler, group,
+                                              self.atlas_options, &mut resized,
+                                              rendered);
                 match res {
-                    Ok(res) => GlyphStateInCache::Present(res),
+                    Ok(res) => res,
                     Err(x) => {
                         err = Some(x);
                         GlyphStateInCache::Null
@@ -420,64 +2187,289 @@ impl<AtlasID: Copy, AtlasCoords: Copy> TextHandler<AtlasID, AtlasCoords> {
                 }
             }
         });
-        if let Some(e) = err { Err(e) }
-        else {
-            Ok(match &ret {
-                GlyphStateInCache::Null => None,
+        if !resized.is_empty() {
+            self.rederive_coords(handler, &resized)?;
+        }
+        if let Some(e) = err { return Err(e) }
+        Ok(match &self.glyphs[&key] {
+            GlyphStateInCache::Null => None,
+            #[cfg(feature="bg-render")]
+            GlyphStateInCache::Pending => None,
+            GlyphStateInCache::Empty(_) => None,
+            GlyphStateInCache::Present(ret)
+                => Some((ret.atlas, ret.coords, ret.info.format)),
+        })
+    }
+    /// Starts rendering the glyphs that `set_render_params` queued up (or,
+    /// in the foreground, renders the next few of them), and swaps in the
+    /// glyphs of any face that's done.
+    fn advance_rerenders<A>(&mut self, handler: &mut A,
+                            resized: &mut Vec<usize>)
+    where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+        let mut finished = vec![];
+        let mut budget = self.rerender_batch;
+        for (&face, rerender) in self.rerenders.iter_mut() {
+            let face_state = self.faces.get(&face)
+                .expect("Rerendering a face that doesn't exist");
+            while let Some(&(glyph, tier)) = rerender.todo.last() {
+                let render_in_bg;
+                #[cfg(feature="bg-render")] {
+                    render_in_bg = self.render_in_bg;
+                }
+                #[cfg(not(feature="bg-render"))] { render_in_bg = false; }
+                if !render_in_bg {
+                    // Leave the rest for the next call.
+                    if budget == 0 { break }
+                    budget -= 1;
+                }
+                rerender.todo.pop();
+                let key = face_state.glyph_key(face, glyph, tier);
+                let (atlas_w, atlas_h) = max_atlas_size(handler, key.2);
+                let (atlas_w, atlas_h) = self.atlas_options.spacing
+                    .usable_size(atlas_w, atlas_h);
+                let oversize = self.oversize_glyphs;
                 #[cfg(feature="bg-render")]
-                GlyphStateInCache::Pending => None,
-                GlyphStateInCache::Present(ret)
-                    => Some((ret.atlas, ret.coords)),
-            })
+                if render_in_bg {
+                    self.bg.render_glyph(face, GlyphId(glyph), tier,
+                                         atlas_w, atlas_h, oversize);
+                    rerender.in_flight.insert((glyph, tier.to_bits()));
+                    continue
+                }
+                let start = Instant::now();
+                let rendered = face_state.render_glyph(GlyphId(glyph), tier,
+                                                       atlas_w, atlas_h,
+                                                       oversize);
+                self.render_stats.count(&rendered, start.elapsed(), false);
+                if let Some(state) = cache_rerendered(&mut self.atlases,
+                                                      handler, key,
+                                                      self.atlas_options,
+                                                      resized, rendered) {
+                    rerender.staged.push((key, state));
+                }
+            }
+            if rerender.todo.is_empty() && rerender.in_flight.is_empty() {
+                finished.push(face)
+            }
+        }
+        for face in finished {
+            let rerender = self.rerenders.remove(&face).unwrap();
+            for (key, state) in rerender.staged {
+                if let Some(GlyphStateInCache::Present(old))
+                    = self.glyphs.insert(key, state) {
+                    self.atlases[old.atlas_index].free(old.info.atlas_rect);
+                }
+            }
         }
     }
+    /// Asks the handler for new `AtlasCoords` for every glyph in the given
+    /// (just resized) atlases.
+    fn rederive_coords<A>(&mut self, handler: &mut A, atlases: &[usize])
+        -> Result<(), Error<A::E>>
+    where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+        let staged = self.rerenders.values_mut()
+            .flat_map(|x| x.staged.iter_mut().map(|(_, state)| state));
+        for state in self.glyphs.values_mut().chain(staged) {
+            if let GlyphStateInCache::Present(state) = state {
+                if atlases.contains(&state.atlas_index) {
+                    state.coords = handler.rederive_coords(state.atlas,
+                                                           state.coords,
+                                                           &state.info)
+                        .map_err(Error::Atlas)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The largest an atlas in the given group can ever be.
+fn max_atlas_size<A: AtlasHandler>(handler: &mut A, group: usize)
+    -> (u32, u32) {
+    let (w, h) = handler.get_atlas_size(group);
+    match handler.growth() {
+        Some(growth) => {
+            let (max_w, max_h) = growth.get_max_atlas_size(group);
+            (w.max(max_w), h.max(max_h))
+        },
+        None => (w, h),
+    }
+}
+
+/// Turns the result of rendering a glyph into an entry for the glyph cache,
+/// putting it into an atlas if there's anything to put. The indices of any
+/// atlases that had to grow are added to `resized`.
+fn cache_render_result<A, AtlasID: Copy, AtlasCoords: Copy>
+    (atlases: &mut Vec<AtlasState<AtlasID>>, handler: &mut A, group: usize,
+     options: AtlasOptions, resized: &mut Vec<usize>, rendered: RenderResult)
+    -> Result<GlyphStateInCache<AtlasID, AtlasCoords>, Error<A::E>>
+where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+    Ok(match rendered {
+        RenderResult::Missing => GlyphStateInCache::Null,
+        RenderResult::Empty(metrics) => GlyphStateInCache::Empty(metrics),
+        RenderResult::Rendered(rendered) => {
+            let state = put_into_atlas(atlases, handler, group, options,
+                                       resized, rendered)?;
+            GlyphStateInCache::Present(state)
+        },
+    })
+}
+
+/// As `cache_render_result`, for a glyph that's being rendered again with
+/// new parameters. If something goes wrong, logs it and returns `None`, so
+/// that the old glyph is kept.
+fn cache_rerendered<A, AtlasID: Copy, AtlasCoords: Copy>
+    (atlases: &mut Vec<AtlasState<AtlasID>>, handler: &mut A, key: GlyphKey,
+     options: AtlasOptions, resized: &mut Vec<usize>,
+     rendered: Result<RenderResult, TooLarge>)
+    -> Option<GlyphStateInCache<AtlasID, AtlasCoords>>
+where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+    let (face, glyph, group, ..) = key;
+    let rendered = match rendered {
+        Ok(x) => x,
+        Err(TooLarge { width, height }) => {
+            log::error!("Glyph {} of face {}: would be {}x{} texels with the \
+                         new render parameters, too large for an atlas",
+                        glyph, face, width, height);
+            return None
+        },
+    };
+    match cache_render_result(atlases, handler, group, options, resized,
+                              rendered) {
+        Ok(x) => Some(x),
+        Err(_) => {
+            log::error!("Error inserting glyph {} of face {}, rendered with \
+                         new render parameters!", glyph, face);
+            None
+        },
+    }
 }
 
 fn put_into_atlas<A, AtlasID: Copy, AtlasCoords: Copy>
-    (atlases: &mut Vec<AtlasState<AtlasID>>, handler: &mut A,
-     atlas_w: u32, atlas_h: u32,
-     render_x_min: f32, render_y_min: f32,
-     render_x_max: f32, render_y_max: f32,
-     sdf_width_int: u32, sdf_height_int: u32,
-     bitmap: RgbImage)
-    -> Result<GlyphState<AtlasID, AtlasCoords>, A::E>
+    (atlases: &mut Vec<AtlasState<AtlasID>>, handler: &mut A, group: usize,
+     options: AtlasOptions, resized: &mut Vec<usize>, rendered: RenderedGlyph)
+    -> Result<GlyphState<AtlasID, AtlasCoords>, Error<A::E>>
 where A: AtlasHandler<AtlasID=AtlasID, AtlasCoords=AtlasCoords> {
+    let RenderedGlyph {
+        render_x_min, render_y_min,
+        render_x_max, render_y_max,
+        sdf_width_int, sdf_height_int,
+        format, pixels, metrics, distance_range,
+    } = rendered;
     // put it in the atlas
     let mut fit = None;
-    for state in atlases.iter_mut() {
+    for (index, state) in atlases.iter_mut().enumerate()
+        .filter(|(_, x)| x.group == group && x.format == format) {
         if let Some((x, y)) = state.attempt_fit(sdf_width_int,
                                                 sdf_height_int) {
-            fit = Some((state.handle, x, y));
+            fit = Some((index, x, y));
             break;
         }
     }
-    let (atlas_handle, atlas_x, atlas_y) = match fit {
+    if fit.is_none() {
+        // Only the newest atlas of a kind can have room left to grow.
+        if let Some(index) = atlases.iter()
+            .rposition(|x| x.group == group && x.format == format) {
+            fit = grow_to_fit(atlases, handler, index, options, resized,
+                              sdf_width_int, sdf_height_int)?;
+        }
+    }
+    let (atlas_index, atlas_x, atlas_y) = match fit {
         Some(x) => x,
         None => {
-            let handle = handler.new_atlas()?;
-            atlases.push(AtlasState::new(handle,
-                                              atlas_w, atlas_h));
+            let mut siblings = atlases.iter()
+                .filter(|x| x.group == group && x.format == format);
+            let layer = siblings.clone().count() as u32;
+            let (handle, (atlas_w, atlas_h)) = match options.mode {
+                AtlasMode::Separate => {
+                    (handler.new_atlas(group, format).map_err(Error::Atlas)?,
+                     handler.get_atlas_size(group))
+                },
+                AtlasMode::Layers { max_layers } => {
+                    if layer >= max_layers {
+                        return Err(Error::OutOfLayers { group, format })
+                    }
+                    // New layers match the array, which may have grown.
+                    let (w, h) = siblings.next().map(|x| x.size())
+                        .unwrap_or_else(|| handler.get_atlas_size(group));
+                    (handler.new_atlas_layer(group, format, layer, w, h)
+                     .map_err(Error::Atlas)?, (w, h))
+                },
+            };
+            atlases.push(AtlasState::new(handle, group, format, layer,
+                                         &options, atlas_w, atlas_h));
+            let index = atlases.len() - 1;
             let state = atlases.last_mut().unwrap();
-            if let Some((x, y)) = state.attempt_fit(sdf_width_int,
-                                                    sdf_height_int) {
-                (state.handle, x, y)
-            }
-            else {
-                // We have made sure that sdf_width_int and
-                // sdf_height_int are at least as large as our atlases.
-                // This case will never arise.
-                unreachable!();
+            match state.attempt_fit(sdf_width_int, sdf_height_int) {
+                Some((x, y)) => (index, x, y),
+                None => grow_to_fit(atlases, handler, index, options, resized,
+                                    sdf_width_int, sdf_height_int)?
+                    // We have made sure that sdf_width_int and
+                    // sdf_height_int are no larger than our atlases can
+                    // get. This case will never arise.
+                    .expect("glyph doesn't fit in a fresh atlas"),
             }
         },
     };
+    let atlas_handle = atlases[atlas_index].handle;
+    let atlas_layer = atlases[atlas_index].layer;
     let coords = handler.add_to_atlas(atlas_handle,
                                       render_x_min, render_y_min,
                                       render_x_max, render_y_max,
                                       atlas_x, atlas_y,
                                       sdf_width_int, sdf_height_int,
-                                      bitmap.as_flat_samples().as_slice())?;
+                                      &pixels).map_err(Error::Atlas)?;
     Ok(GlyphState {
+        atlas_index,
         atlas: atlas_handle,
         coords,
+        info: GlyphInfo {
+            render_x_min, render_y_min,
+            render_x_max, render_y_max,
+            atlas_rect: Rect {
+                x: atlas_x, y: atlas_y, w: sdf_width_int, h: sdf_height_int,
+            },
+            atlas_layer,
+            format,
+            metrics,
+            distance_range_texels: distance_range,
+            distance_range_ems_x: distance_range
+                * (render_x_max - render_x_min) / sdf_width_int as f32,
+            distance_range_ems_y: distance_range
+                * (render_y_max - render_y_min) / sdf_height_int as f32,
+        },
     })
 }
+
+/// Grows the given atlas until a `w`×`h` glyph fits in it, or until it can't
+/// grow any more. Returns where the glyph went, if it fit.
+///
+/// In `AtlasMode::Layers`, the other layers of the same array grow along
+/// with it.
+fn grow_to_fit<A, AtlasID: Copy>
+    (atlases: &mut [AtlasState<AtlasID>], handler: &mut A, index: usize,
+     options: AtlasOptions, resized: &mut Vec<usize>, w: u32, h: u32)
+    -> GlyphResult<(usize, u32, u32), A::E>
+where A: AtlasHandler<AtlasID=AtlasID> {
+    let (group, format) = (atlases[index].group, atlases[index].format);
+    let max = max_atlas_size(handler, group);
+    let growth = match handler.growth() {
+        Some(growth) => growth,
+        None => return Ok(None),
+    };
+    while let Some((new_w, new_h)) = atlases[index].next_size(max) {
+        growth.resize_atlas(atlases[index].handle, new_w, new_h)
+            .map_err(Error::Atlas)?;
+        for (other, state) in atlases.iter_mut().enumerate() {
+            let same_array
+                = matches!(options.mode, AtlasMode::Layers { .. })
+                && state.group == group && state.format == format;
+            if other != index && !same_array { continue }
+            state.resize(new_w, new_h);
+            if !resized.contains(&other) { resized.push(other) }
+        }
+        if let Some((x, y)) = atlases[index].attempt_fit(w, h) {
+            return Ok(Some((index, x, y)))
+        }
+    }
+    Ok(None)
+}
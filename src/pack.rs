@@ -0,0 +1,357 @@
+//! Rectangle packers for atlases. Glyphs arrive one at a time, in whatever
+//! order they're first used, so these are all online algorithms: each glyph
+//! is placed as it comes, and never moved afterward.
+
+/// Decides where glyphs go within a single atlas. Implement this if none of
+/// the packers provided here suit your glyph mix, and use
+/// [`Packing::Custom`](enum.Packing.html#variant.Custom) to plug it in.
+///
+/// Packers only see the part of the atlas inside the border, in units of
+/// the alignment, and rectangles that already include the space between
+/// glyphs. (See [`AtlasSpacing`](struct.AtlasSpacing.html).) With the
+/// default spacing, these are plain texels.
+pub trait AtlasPacker: Send {
+    /// The current size of the area being packed.
+    fn size(&self) -> (u32, u32);
+    /// Find room for a `w`×`h` rectangle, mark it as used, and return its
+    /// position. Return `None` if it doesn't fit anywhere.
+    fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)>;
+    /// Enlarge the area being packed to `w`×`h`. Neither dimension will
+    /// shrink, and rectangles already packed stay where they are.
+    fn resize(&mut self, w: u32, h: u32);
+    /// Mark a rectangle that `pack` returned earlier as free again, so that
+    /// later rectangles can go there.
+    ///
+    /// The default does nothing, and the space is lost. (An atlas whose
+    /// glyphs have all been freed gets a fresh packer either way.)
+    fn free(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        let _ = (x, y, w, h);
+    }
+    /// Return the size of the largest rectangle that would still fit, as
+    /// best you can tell, for
+    /// [`AtlasOccupancy::largest_free`](struct.AtlasOccupancy.html#structfield.largest_free).
+    /// (Largest by area, if there's more than one shape to choose from.)
+    ///
+    /// The default returns `None`, for "don't know".
+    fn largest_free_rect(&self) -> Option<(u32, u32)> {
+        None
+    }
+}
+
+/// Which packer each new atlas gets. Set with
+/// [`set_packing`](struct.TextHandler.html#method.set_packing), and compare
+/// the results with
+/// [`get_atlas_occupancy`](struct.TextHandler.html#method.get_atlas_occupancy).
+#[derive(Clone,Copy,Debug,Default)]
+pub enum Packing {
+    /// [`SkylinePacker`](struct.SkylinePacker.html). This is the default.
+    #[default]
+    Skyline,
+    /// [`MaxRectsPacker`](struct.MaxRectsPacker.html).
+    MaxRects,
+    /// [`ShelfPacker`](struct.ShelfPacker.html).
+    Shelf,
+    /// Your own packer. The function is called with the size of the area
+    /// to pack in each new atlas.
+    Custom(fn(u32, u32) -> Box<dyn AtlasPacker>),
+}
+
+impl Packing {
+    pub(crate) fn new_packer(&self, w: u32, h: u32) -> Box<dyn AtlasPacker> {
+        match self {
+            Packing::Skyline => Box::new(SkylinePacker::new(w, h)),
+            Packing::MaxRects => Box::new(MaxRectsPacker::new(w, h)),
+            Packing::Shelf => Box::new(ShelfPacker::new(w, h)),
+            Packing::Custom(f) => f(w, h),
+        }
+    }
+}
+
+/// Skyline bottom-left: keeps track of the top edge of the packed area, and
+/// puts each rectangle wherever along it leaves its top edge lowest. Fast,
+/// and good when glyphs are of similar heights, but the space under an
+/// overhang is lost for good. Freed rectangles lower the skyline again
+/// wherever nothing was stacked on top of them.
+pub struct SkylinePacker {
+    w: u32, h: u32,
+    /// The top edge, from left to right: where each step starts, and how
+    /// high it is. The first starts at zero, and they run to the right edge.
+    skyline: Vec<(u32, u32)>,
+}
+
+impl SkylinePacker {
+    pub fn new(w: u32, h: u32) -> SkylinePacker {
+        SkylinePacker { w, h, skyline: vec![(0, 0)] }
+    }
+    /// Finds the lowest place for a `w`×`h` rectangle, leftmost if there's a
+    /// tie. Rectangles always start at the start of a step.
+    fn find(&self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w == 0 || h == 0 || w > self.w { return None }
+        let mut best: Option<(u32, u32)> = None;
+        for (i, &(x, _)) in self.skyline.iter().enumerate() {
+            if self.w - x < w { break }
+            // The rectangle rests on the highest step under it.
+            let mut y = 0;
+            let mut j = i;
+            while j < self.skyline.len() && self.skyline[j].0 < x + w {
+                y = y.max(self.skyline[j].1);
+                j += 1;
+            }
+            if self.h - y.min(self.h) < h { continue }
+            if best.is_none_or(|best| y < best.1) { best = Some((x, y)) }
+        }
+        best
+    }
+    /// Changes the height of the skyline from `x0` to `x1`, step by step, to
+    /// whatever `f` returns for the step's current height. Steps that `f`
+    /// returns `None` for are left alone.
+    fn set_span(&mut self, x0: u32, x1: u32, f: impl Fn(u32) -> Option<u32>) {
+        // Split the steps at both ends of the span, so every step is either
+        // inside it or outside it.
+        for at in [x0, x1] {
+            if at >= self.w { continue }
+            let i = self.skyline.partition_point(|step| step.0 <= at) - 1;
+            if self.skyline[i].0 != at {
+                self.skyline.insert(i + 1, (at, self.skyline[i].1));
+            }
+        }
+        for step in self.skyline.iter_mut() {
+            if step.0 >= x0 && step.0 < x1 {
+                if let Some(y) = f(step.1) { step.1 = y }
+            }
+        }
+        self.skyline.dedup_by(|later, earlier| later.1 == earlier.1);
+    }
+}
+
+impl AtlasPacker for SkylinePacker {
+    fn size(&self) -> (u32, u32) {
+        (self.w, self.h)
+    }
+    fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        let (x, y) = self.find(w, h)?;
+        self.set_span(x, x + w, |_| Some(y + h));
+        Some((x, y))
+    }
+    fn resize(&mut self, w: u32, h: u32) {
+        if w > self.w {
+            let last = self.skyline.len() - 1;
+            if self.skyline[last].1 != 0 { self.skyline.push((self.w, 0)) }
+        }
+        self.w = w;
+        self.h = h;
+    }
+    fn free(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        // Wherever the skyline is still the top of this rectangle, nothing
+        // was put on top of it, so the skyline can come back down to its
+        // bottom. (Anything under it was already given up for lost.)
+        self.set_span(x, x + w, |top| (top == y + h).then_some(y));
+    }
+    /// An estimate: the widest rectangle that fits is found for the full
+    /// height, half of it, a quarter of it, and so on, and the biggest of
+    /// those wins.
+    fn largest_free_rect(&self) -> Option<(u32, u32)> {
+        let (w, mut h) = self.size();
+        let mut best = (0, 0);
+        while h > 0 {
+            let (mut lo, mut hi) = (0, w);
+            while lo < hi {
+                let mid = (lo + hi).div_ceil(2);
+                if self.find(mid, h).is_some() { lo = mid }
+                else { hi = mid - 1 }
+            }
+            if lo as u64 * h as u64 > best.0 as u64 * best.1 as u64 {
+                best = (lo, h);
+            }
+            h /= 2;
+        }
+        Some(best)
+    }
+}
+
+#[derive(Clone,Copy,Debug)]
+struct FreeRect {
+    x: u32, y: u32, w: u32, h: u32,
+}
+
+impl FreeRect {
+    fn contains(&self, other: &FreeRect) -> bool {
+        other.x >= self.x && other.y >= self.y
+            && other.x + other.w <= self.x + self.w
+            && other.y + other.h <= self.y + self.h
+    }
+}
+
+/// MaxRects, best short side fit: keeps track of every maximal free
+/// rectangle, and puts each rectangle in the free one it fills most snugly
+/// along its shorter leftover side. Packs tightest, especially with a mix of
+/// glyph sizes, but gets slower as the atlas fills up.
+pub struct MaxRectsPacker {
+    w: u32, h: u32,
+    free: Vec<FreeRect>,
+}
+
+impl MaxRectsPacker {
+    pub fn new(w: u32, h: u32) -> MaxRectsPacker {
+        MaxRectsPacker { w, h, free: vec![FreeRect { x: 0, y: 0, w, h }] }
+    }
+    /// Removes free rectangles that are inside other free rectangles.
+    fn prune(&mut self) {
+        let mut i = 0;
+        while i < self.free.len() {
+            let redundant = self.free.iter().enumerate().any(|(j, other)| {
+                // (of two identical rectangles, keep the first)
+                j != i && other.contains(&self.free[i])
+                    && (j < i || !self.free[i].contains(other))
+            });
+            if redundant { self.free.swap_remove(i); }
+            else { i += 1 }
+        }
+    }
+}
+
+impl AtlasPacker for MaxRectsPacker {
+    fn size(&self) -> (u32, u32) {
+        (self.w, self.h)
+    }
+    fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w == 0 || h == 0 { return None }
+        let best = self.free.iter()
+            .filter(|free| free.w >= w && free.h >= h)
+            .min_by_key(|free| {
+                let (left_w, left_h) = (free.w - w, free.h - h);
+                (left_w.min(left_h), left_w.max(left_h), free.y, free.x)
+            })?;
+        let used = FreeRect { x: best.x, y: best.y, w, h };
+        // Split every free rectangle that overlaps the new one into the
+        // (up to four) maximal rectangles around it.
+        let mut split = Vec::with_capacity(self.free.len() + 4);
+        for free in self.free.drain(..) {
+            if used.x >= free.x + free.w || used.x + used.w <= free.x
+            || used.y >= free.y + free.h || used.y + used.h <= free.y {
+                split.push(free);
+                continue
+            }
+            if used.x > free.x {
+                split.push(FreeRect { w: used.x - free.x, ..free });
+            }
+            if used.x + used.w < free.x + free.w {
+                split.push(FreeRect { x: used.x + used.w,
+                                      w: free.x + free.w - used.x - used.w,
+                                      ..free });
+            }
+            if used.y > free.y {
+                split.push(FreeRect { h: used.y - free.y, ..free });
+            }
+            if used.y + used.h < free.y + free.h {
+                split.push(FreeRect { y: used.y + used.h,
+                                      h: free.y + free.h - used.y - used.h,
+                                      ..free });
+            }
+        }
+        self.free = split;
+        self.prune();
+        Some((used.x, used.y))
+    }
+    fn resize(&mut self, w: u32, h: u32) {
+        // Free rectangles that touch the old edges extend into the new
+        // space, and the new space is free in its own right.
+        for free in self.free.iter_mut() {
+            if free.x + free.w == self.w { free.w = w - free.x }
+            if free.y + free.h == self.h { free.h = h - free.y }
+        }
+        if w > self.w {
+            self.free.push(FreeRect { x: self.w, y: 0, w: w - self.w, h });
+        }
+        if h > self.h {
+            self.free.push(FreeRect { x: 0, y: self.h, w, h: h - self.h });
+        }
+        self.w = w;
+        self.h = h;
+        self.prune();
+    }
+    fn free(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        // (This isn't merged with the free rectangles around it, so a
+        // rectangle bigger than this one won't fit here, even if there's
+        // room.)
+        self.free.push(FreeRect { x, y, w, h });
+        self.prune();
+    }
+    fn largest_free_rect(&self) -> Option<(u32, u32)> {
+        Some(self.free.iter()
+             .map(|free| (free.w, free.h))
+             .max_by_key(|&(w, h)| w as u64 * h as u64)
+             .unwrap_or((0, 0)))
+    }
+}
+
+struct Shelf {
+    y: u32, h: u32,
+    /// How much of the shelf's width is used, from the left.
+    used: u32,
+}
+
+/// Shelf packing, best height fit: stacks rows ("shelves") of rectangles,
+/// putting each rectangle on the shelf that's closest to its height, or on a
+/// new shelf if none has room. Simplest and fastest, and nearly as good as
+/// the others when glyphs are all about the same height, as they are for a
+/// single face and script.
+pub struct ShelfPacker {
+    w: u32, h: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    pub fn new(w: u32, h: u32) -> ShelfPacker {
+        ShelfPacker { w, h, shelves: vec![] }
+    }
+}
+
+impl AtlasPacker for ShelfPacker {
+    fn size(&self) -> (u32, u32) {
+        (self.w, self.h)
+    }
+    fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w == 0 || h == 0 || w > self.w { return None }
+        let atlas_w = self.w;
+        let best = self.shelves.iter_mut()
+            .filter(|shelf| shelf.h >= h && atlas_w - shelf.used >= w)
+            .min_by_key(|shelf| (shelf.h - h, shelf.y));
+        if let Some(shelf) = best {
+            let x = shelf.used;
+            shelf.used += w;
+            return Some((x, shelf.y))
+        }
+        let top = self.shelves.last().map(|x| x.y + x.h).unwrap_or(0);
+        if self.h - top < h { return None }
+        self.shelves.push(Shelf { y: top, h, used: w });
+        Some((0, top))
+    }
+    fn resize(&mut self, w: u32, h: u32) {
+        self.w = w;
+        self.h = h;
+    }
+    fn free(&mut self, x: u32, y: u32, w: u32, _h: u32) {
+        // Only the last rectangle on a shelf can be given back. If it was
+        // the only one, a shelf at the top goes away entirely.
+        let shelf = match self.shelves.iter_mut()
+            .find(|shelf| shelf.y == y && shelf.used == x + w) {
+                Some(x) => x,
+                None => return,
+            };
+        shelf.used = x;
+        if shelf.used == 0
+        && self.shelves.last().map(|x| x.y) == Some(y) {
+            self.shelves.pop();
+        }
+    }
+    fn largest_free_rect(&self) -> Option<(u32, u32)> {
+        // The rest of each shelf, and everything above the top one.
+        let top = self.shelves.last().map(|x| x.y + x.h).unwrap_or(0);
+        Some(self.shelves.iter()
+             .map(|shelf| (self.w - shelf.used, shelf.h))
+             .chain(std::iter::once((self.w, self.h - top)))
+             .max_by_key(|&(w, h)| w as u64 * h as u64)
+             .unwrap_or((0, 0)))
+    }
+}
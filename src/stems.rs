@@ -0,0 +1,124 @@
+//! Measuring how thick a face's strokes are, for picking a texel density
+//! automatically. See `RenderParams::estimate_texels_per_em`.
+
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// Characters whose glyphs have typical stems and bowls: straight vertical
+/// strokes, round strokes, and a horizontal bar.
+const SAMPLES: &[char] = &['l', 'o', 'H', 'I', 'n'];
+
+/// Where to cut across each glyph, as fractions of its bounding box. Cutting
+/// near the edges would catch curves at a tangent, which look thinner than
+/// they are.
+const CUTS: &[f32] = &[0.25, 0.5, 0.75];
+
+/// How many line segments to flatten each curve into.
+const CURVE_STEPS: usize = 8;
+
+/// Spans thinner than this, as a fraction of an em, are ignored. No real
+/// stroke is that thin, but overlapping contours (especially ones wound
+/// the wrong way) can leave slivers that are.
+const MIN_STROKE: f32 = 0.01;
+
+/// A glyph's outline, flattened into line segments.
+#[derive(Default)]
+struct Lines {
+    lines: Vec<((f32, f32), (f32, f32))>,
+    start: (f32, f32),
+    pen: (f32, f32),
+}
+
+impl Lines {
+    fn line(&mut self, to: (f32, f32)) {
+        self.lines.push((self.pen, to));
+        self.pen = to;
+    }
+    /// Returns the lengths of the spans where a line across the outline is
+    /// inside it. Cuts along x (horizontal lines at height `at`) if
+    /// `horizontal`, otherwise along y.
+    fn spans(&self, at: f32, horizontal: bool) -> Vec<f32> {
+        // (position along the cut, winding direction)
+        let mut crossings: Vec<(f32, i32)> = self.lines.iter()
+            .filter_map(|&(a, b)| {
+                let (a, b) = if horizontal { (a, b) }
+                             else { ((a.1, a.0), (b.1, b.0)) };
+                // Half-open, so that a cut through a vertex counts once.
+                if (a.1 <= at) == (b.1 <= at) { return None }
+                let t = (at - a.1) / (b.1 - a.1);
+                Some((a.0 + (b.0 - a.0) * t, if b.1 > a.1 { 1 } else { -1 }))
+            }).collect();
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut spans = vec![];
+        let mut winding = 0;
+        let mut span_start = 0.0;
+        for (pos, direction) in crossings {
+            let was_inside = winding != 0;
+            winding += direction;
+            if !was_inside && winding != 0 { span_start = pos }
+            else if was_inside && winding == 0 { spans.push(pos - span_start) }
+        }
+        spans
+    }
+}
+
+impl OutlineBuilder for Lines {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = (x, y);
+        self.pen = (x, y);
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.line((x, y));
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let p0 = self.pen;
+        for step in 1 ..= CURVE_STEPS {
+            let t = step as f32 / CURVE_STEPS as f32;
+            let u = 1.0 - t;
+            self.line((u * u * p0.0 + 2.0 * u * t * x1 + t * t * x,
+                       u * u * p0.1 + 2.0 * u * t * y1 + t * t * y));
+        }
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32,
+                x: f32, y: f32) {
+        let p0 = self.pen;
+        for step in 1 ..= CURVE_STEPS {
+            let t = step as f32 / CURVE_STEPS as f32;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t,
+                                t * t * t);
+            self.line((a * p0.0 + b * x1 + c * x2 + d * x,
+                       a * p0.1 + b * y1 + c * y2 + d * y));
+        }
+    }
+    fn close(&mut self) {
+        if self.pen != self.start { self.line(self.start) }
+    }
+}
+
+/// Returns the width, in font units, of the thinnest stroke among the
+/// sample glyphs that the face has, or `None` if it has none of them (or
+/// they have no outlines). Slivers thinner than `MIN_STROKE` don't count.
+pub(crate) fn thinnest_stroke(face: &Face) -> Option<f32> {
+    let min_stroke = face.units_per_em() as f32 * MIN_STROKE;
+    let mut thinnest: Option<f32> = None;
+    for glyph in SAMPLES.iter().filter_map(|&c| face.glyph_index(c)) {
+        let mut lines = Lines::default();
+        let bbox = match face.outline_glyph(GlyphId(glyph.0), &mut lines) {
+            Some(x) => x,
+            None => continue,
+        };
+        for &cut in CUTS {
+            let y = bbox.y_min as f32
+                + (bbox.y_max as f32 - bbox.y_min as f32) * cut;
+            let x = bbox.x_min as f32
+                + (bbox.x_max as f32 - bbox.x_min as f32) * cut;
+            for span in lines.spans(y, true).into_iter()
+                .chain(lines.spans(x, false)) {
+                if span >= min_stroke {
+                    thinnest = Some(thinnest.map_or(span, |x| x.min(span)));
+                }
+            }
+        }
+    }
+    thinnest
+}
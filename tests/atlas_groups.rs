@@ -0,0 +1,90 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{AtlasFormat, MsdfConfig};
+use common::{MemoryAtlases, TestFont, handler, msdf_inside};
+
+fn font() -> (Arc<Vec<u8>>, u16) {
+    let mut font = TestFont::new(1000);
+    let rect = font.rect(100, 0, 500, 700);
+    (Arc::new(font.build()), rect)
+}
+
+#[test]
+fn faces_go_into_their_own_groups() {
+    let (data, rect) = font();
+    let mut handler = handler();
+    let hud = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let big = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    assert_eq!(handler.get_atlas_group(big), Some(0));
+    handler.set_atlas_group(big, 7).unwrap();
+    assert_eq!(handler.get_atlas_group(big), Some(7));
+    let mut atlases = MemoryAtlases::new(64, 64);
+    atlases.group_sizes.insert(7, (512, 256));
+    let (hud_atlas, _, _) = handler.get_glyph(hud, rect, &mut atlases)
+        .unwrap().unwrap();
+    let (big_atlas, _, _) = handler.get_glyph(big, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_ne!(hud_atlas, big_atlas);
+    assert_eq!(atlases.atlases, vec![(0, AtlasFormat::Msdf),
+                                     (7, AtlasFormat::Msdf)]);
+    // Another glyph in the same group shares the atlas.
+    let other = handler.add_face_variant(big, Default::default()).unwrap();
+    assert_eq!(handler.get_atlas_group(other), Some(7));
+    let (other_atlas, _, _) = handler.get_glyph(other, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(other_atlas, big_atlas);
+    // Moving a face re-renders its glyphs into the new group.
+    handler.set_atlas_group(big, 0).unwrap();
+    let (moved_atlas, _, _) = handler.get_glyph(big, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(moved_atlas, hud_atlas);
+}
+
+#[test]
+fn group_sizes_limit_glyph_sizes() {
+    let (data, rect) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 256.0, 256.0).unwrap();
+    handler.set_atlas_group(face, 1).unwrap();
+    let mut atlases = MemoryAtlases::new(512, 512);
+    atlases.group_sizes.insert(1, (64, 64));
+    handler.get_glyph(face, rect, &mut atlases).unwrap().unwrap();
+    let info = handler.get_glyph_info(face, rect).unwrap();
+    // shrunk to fit the group's atlases, not the default size
+    assert_eq!(info.atlas_rect.h, 64);
+}
+
+#[test]
+fn mtsdf_glyphs_get_their_own_atlases() {
+    let (data, rect) = font();
+    let mut handler = handler();
+    let msdf = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let mtsdf = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    handler.set_msdf_config(mtsdf, MsdfConfig {
+        mtsdf: true,
+        ..MsdfConfig::default()
+    }).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let (_, _, format) = handler.get_glyph(msdf, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(format, AtlasFormat::Msdf);
+    let (_, coords, format) = handler.get_glyph(mtsdf, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(format, AtlasFormat::Mtsdf);
+    assert_eq!(atlases.atlases, vec![(0, AtlasFormat::Msdf),
+                                     (0, AtlasFormat::Mtsdf)]);
+    let placed = &atlases.placed[coords];
+    assert_eq!(placed.pixels.len(),
+               (placed.width * placed.height * 4) as usize);
+    let (cx, cy) = (placed.width / 2, placed.height / 2);
+    assert!(msdf_inside(placed, cx, cy));
+    assert!(!msdf_inside(placed, 0, 0));
+    // The alpha channel is a true distance: inside in the middle, outside
+    // in the corner.
+    let alpha = |x: u32, y: u32| {
+        placed.pixels[((y * placed.width + x) * 4 + 3) as usize]
+    };
+    assert!(alpha(cx, cy) > 127);
+    assert!(alpha(0, 0) < 128);
+}
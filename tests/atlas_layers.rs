@@ -0,0 +1,63 @@
+mod common;
+
+use psilo_text::{AtlasFormat, AtlasMode, Error, TextHandler};
+use common::{MemoryAtlases, square_font};
+
+fn handler(max_layers: u32) -> TextHandler<usize, usize> {
+    let mut handler = common::handler();
+    handler.set_atlas_mode(AtlasMode::Layers { max_layers });
+    handler
+}
+
+#[test]
+fn glyphs_report_their_layer() {
+    let (data, glyphs) = square_font(10);
+    let mut handler = handler(8);
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 32);
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    // Two glyphs to a layer.
+    let layers: Vec<_> = glyphs.iter().map(|&glyph| {
+        handler.get_glyph_info(face, glyph).unwrap().atlas_layer
+    }).collect();
+    assert_eq!(layers, vec![0, 0, 1, 1, 2, 2, 3, 3, 4, 4]);
+    assert_eq!(atlases.layers,
+               vec![Some(0), Some(1), Some(2), Some(3), Some(4)]);
+}
+
+#[test]
+fn running_out_of_layers_is_an_error() {
+    let (data, glyphs) = square_font(10);
+    let mut handler = handler(2);
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 32);
+    for &glyph in glyphs[.. 4].iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    match handler.get_glyph(face, glyphs[4], &mut atlases) {
+        Err(Error::OutOfLayers { group: 0, format: AtlasFormat::Msdf }) => (),
+        x => panic!("expected OutOfLayers, got {:?}", x.map(|_| ())),
+    }
+    assert_eq!(atlases.atlases.len(), 2);
+}
+
+#[test]
+fn layers_grow_together() {
+    let (data, glyphs) = square_font(10);
+    let mut handler = handler(2);
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 32);
+    atlases.max_size = Some((64, 64));
+    for &glyph in glyphs[.. 8].iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    // The first layer grows to hold four glyphs. The second one starts out
+    // at that size, rather than the initial one.
+    assert_eq!(atlases.sizes, vec![(64, 64), (64, 64)]);
+    let layers: Vec<_> = glyphs[.. 8].iter().map(|&glyph| {
+        handler.get_glyph_info(face, glyph).unwrap().atlas_layer
+    }).collect();
+    assert_eq!(layers, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{AtlasSpacing, Packing};
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font with glyphs of assorted sizes, and one that fills a whole em.
+fn font() -> (Arc<Vec<u8>>, Vec<u16>) {
+    let mut font = TestFont::new(1000);
+    let mut glyphs = font.assorted_rects(20, (500, 600));
+    glyphs.push(font.rect(0, 0, 1000, 1000));
+    (Arc::new(font.build()), glyphs)
+}
+
+fn fill(packing: Packing, spacing: AtlasSpacing) -> MemoryAtlases {
+    let (data, glyphs) = font();
+    let mut handler = handler();
+    handler.set_packing(packing);
+    handler.set_atlas_spacing(spacing);
+    // Big enough that the last glyph has to be shrunk to fit inside the
+    // border.
+    let face = handler.add_face(data, 0, 4.0, 60.0, 60.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    atlases
+}
+
+#[test]
+fn glyphs_keep_their_distance() {
+    let spacing = AtlasSpacing { between_glyphs: 2, border: 3, alignment: 1 };
+    for packing in [Packing::Skyline, Packing::MaxRects, Packing::Shelf] {
+        let atlases = fill(packing, spacing);
+        for (i, a) in atlases.placed.iter().enumerate() {
+            let (w, h) = atlases.sizes[a.atlas];
+            assert!(a.x >= 3 && a.y >= 3
+                    && a.x + a.width + 3 <= w && a.y + a.height + 3 <= h,
+                    "{:?} is in the border", (a.x, a.y, a.width, a.height));
+            for b in atlases.placed[i+1 ..].iter() {
+                assert!(a.atlas != b.atlas
+                        || a.x + a.width + 2 <= b.x
+                        || b.x + b.width + 2 <= a.x
+                        || a.y + a.height + 2 <= b.y
+                        || b.y + b.height + 2 <= a.y,
+                        "{:?} is too close to {:?}",
+                        (a.x, a.y, a.width, a.height),
+                        (b.x, b.y, b.width, b.height));
+            }
+        }
+    }
+}
+
+#[test]
+fn glyphs_are_aligned_to_blocks() {
+    let spacing = AtlasSpacing { between_glyphs: 1, border: 1, alignment: 4 };
+    for packing in [Packing::Skyline, Packing::MaxRects, Packing::Shelf] {
+        let atlases = fill(packing, spacing);
+        let mut blocks = vec![];
+        for placed in atlases.placed.iter() {
+            assert_eq!((placed.x % 4, placed.y % 4), (0, 0));
+            // The border rounds up to a whole block.
+            assert!(placed.x >= 4 && placed.y >= 4);
+            for y in placed.y / 4 .. (placed.y + placed.height).div_ceil(4) {
+                for x in placed.x / 4 .. (placed.x + placed.width).div_ceil(4) {
+                    blocks.push((placed.atlas, x, y));
+                }
+            }
+        }
+        // No two glyphs share a block.
+        let count = blocks.len();
+        blocks.sort();
+        blocks.dedup();
+        assert_eq!(blocks.len(), count);
+    }
+}
+
+#[test]
+fn no_spacing_by_default() {
+    let atlases = fill(Packing::Skyline, AtlasSpacing::default());
+    assert_eq!((atlases.placed[0].x, atlases.placed[0].y), (0, 0));
+    // The big glyph gets the whole atlas.
+    let big = atlases.placed.last().unwrap();
+    assert_eq!((big.width, big.height), (64, 64));
+}
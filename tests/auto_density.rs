@@ -0,0 +1,101 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{RenderParams, TextHandler};
+use common::TestFont;
+
+/// Adds an 'l' with a 100 unit stem and an 'o' (a square ring) with 80 unit
+/// strokes.
+fn l_and_o(font: &mut TestFont) {
+    let l = font.rect(0, 0, 100, 700);
+    font.map('l', l);
+    let o = font.glyph(&[&[(0, 0), (0, 600), (600, 600), (600, 0)],
+                         &[(80, 80), (520, 80), (520, 520), (80, 520)]],
+                       600);
+    font.map('o', o);
+}
+
+/// Adds an 'H' with 120 unit stems and a 60 unit crossbar.
+fn h(font: &mut TestFont) {
+    let h = font.glyph(&[&[(0, 0), (0, 700), (120, 700), (120, 380),
+                           (380, 380), (380, 700), (500, 700), (500, 0),
+                           (380, 0), (380, 320), (120, 320), (120, 0)]],
+                       500);
+    font.map('H', h);
+}
+
+#[test]
+fn the_thinnest_stroke_decides() {
+    let mut font = TestFont::new(1000);
+    l_and_o(&mut font);
+    let data = font.build();
+    let face = ttf_parser::Face::parse(&data, 0).unwrap();
+    // The 'o' is thinner than the 'l': 3 texels across 80 units.
+    let estimate = RenderParams::estimate_texels_per_em(&face, 3.0).unwrap();
+    assert!((estimate - 37.5).abs() < 0.01, "{}", estimate);
+    // And the crossbar of the 'H' is thinner still.
+    let mut font = TestFont::new(1000);
+    l_and_o(&mut font);
+    h(&mut font);
+    let data = font.build();
+    let face = ttf_parser::Face::parse(&data, 0).unwrap();
+    let params = RenderParams::auto(&face, 4.0, 3.0).unwrap();
+    assert!((params.texels_per_em_x - 50.0).abs() < 0.01, "{:?}", params);
+    assert_eq!(params.texels_per_em_x, params.texels_per_em_y);
+    assert_eq!(params.distance_range, 4.0);
+}
+
+#[test]
+fn slivers_between_overlapping_contours_are_ignored() {
+    let mut font = TestFont::new(1000);
+    l_and_o(&mut font);
+    // An 'I' with a stray contour wound the wrong way, overlapping the
+    // right edge of its stem. Under nonzero winding, that leaves a hole one
+    // unit wide and a sliver one unit wide beside it.
+    let i = font.glyph(&[&[(0, 0), (0, 700), (100, 700), (100, 0)],
+                         &[(99, 0), (101, 0), (101, 700), (99, 700)]], 100);
+    font.map('I', i);
+    let data = font.build();
+    let face = ttf_parser::Face::parse(&data, 0).unwrap();
+    // The 'o' still decides.
+    let estimate = RenderParams::estimate_texels_per_em(&face, 3.0).unwrap();
+    assert!((estimate - 37.5).abs() < 0.01, "{}", estimate);
+}
+
+#[test]
+fn the_estimate_is_capped() {
+    // Strokes 12 units across, in a 1000 unit em: thin, but real.
+    let mut font = TestFont::new(1000);
+    let l = font.rect(0, 0, 12, 700);
+    font.map('l', l);
+    let data = font.build();
+    let face = ttf_parser::Face::parse(&data, 0).unwrap();
+    assert_eq!(RenderParams::estimate_texels_per_em(&face, 3.0), Some(250.0));
+    assert_eq!(RenderParams::estimate_texels_per_em(&face, 4.0), Some(256.0));
+}
+
+#[test]
+fn faces_without_samples_fall_back() {
+    let mut font = TestFont::new(1000);
+    let square = font.rect(0, 0, 500, 500);
+    font.map('\u{25A0}', square);
+    let data = Arc::new(font.build());
+    let face = ttf_parser::Face::parse(&data, 0).unwrap();
+    assert_eq!(RenderParams::estimate_texels_per_em(&face, 3.0), None);
+    let mut handler = TextHandler::<usize, usize>::new();
+    let face = handler.add_face_auto(data, 0, 4.0, 3.0).unwrap();
+    assert_eq!(handler.get_render_params(face).unwrap().texels_per_em_x,
+               64.0);
+}
+
+#[test]
+fn add_face_auto_uses_the_estimate() {
+    let mut font = TestFont::new(2048);
+    l_and_o(&mut font);
+    let data = Arc::new(font.build());
+    let mut handler = TextHandler::<usize, usize>::new();
+    let face = handler.add_face_auto(data, 0, 4.0, 2.0).unwrap();
+    let params = handler.get_render_params(face).unwrap();
+    assert!((params.texels_per_em_y - 2.0 * 2048.0 / 80.0).abs() < 0.01,
+            "{:?}", params);
+}
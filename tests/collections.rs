@@ -0,0 +1,80 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::FaceInfo;
+use common::{MemoryAtlases, TestFont, build_collection, handler};
+
+/// A face with a square (glyph 1) that's `size` units across.
+fn face(family: &str, subfamily: &str, weight: u16, italic: bool,
+        size: i16) -> Vec<u8> {
+    let mut font = TestFont::new(1000);
+    font.names(family, subfamily);
+    font.style(weight, italic);
+    font.rect(0, 0, size, size);
+    font.build()
+}
+
+fn collection() -> Vec<u8> {
+    build_collection(&[face("Test Sans", "Regular", 400, false, 500),
+                       face("Test Sans", "Bold Italic", 700, true, 700),
+                       face("Test Serif", "Light", 300, false, 300)])
+}
+
+#[test]
+fn collections_are_listed() {
+    let faces = FaceInfo::list(&collection());
+    assert_eq!(faces, vec![
+        FaceInfo {
+            index: 0, family: Some("Test Sans".to_string()),
+            subfamily: Some("Regular".to_string()),
+            weight: 400, italic: false, glyph_count: 2,
+        },
+        FaceInfo {
+            index: 1, family: Some("Test Sans".to_string()),
+            subfamily: Some("Bold Italic".to_string()),
+            weight: 700, italic: true, glyph_count: 2,
+        },
+        FaceInfo {
+            index: 2, family: Some("Test Serif".to_string()),
+            subfamily: Some("Light".to_string()),
+            weight: 300, italic: false, glyph_count: 2,
+        },
+    ]);
+    assert_eq!(FaceInfo::get(&collection(), 3), None);
+}
+
+#[test]
+fn single_faces_are_listed_too() {
+    let faces = FaceInfo::list(&face("Test Mono", "Regular", 400, false,
+                                     500));
+    assert_eq!(faces.len(), 1);
+    assert_eq!(faces[0].index, 0);
+    assert_eq!(faces[0].family.as_deref(), Some("Test Mono"));
+    // Without names or an `OS/2` table, we get defaults.
+    let mut font = TestFont::new(1000);
+    font.rect(0, 0, 500, 500);
+    let faces = FaceInfo::list(&font.build());
+    assert_eq!(faces, vec![FaceInfo {
+        index: 0, family: None, subfamily: None, weight: 400, italic: false,
+        glyph_count: 2,
+    }]);
+    assert!(FaceInfo::list(b"not a font").is_empty());
+}
+
+#[test]
+fn all_faces_can_be_added_at_once() {
+    let data = Arc::new(collection());
+    let mut handler = handler();
+    let faces = handler.add_all_faces(data.clone(), 4.0, 32.0, 32.0);
+    assert_eq!(faces.len(), 3);
+    let mut atlases = MemoryAtlases::new(128, 128);
+    // Each is its own face, with its own glyphs, from the same bytes.
+    for ((info, face), width) in faces.iter().zip([20, 27, 14]) {
+        assert_eq!(handler.get_face(*face).unwrap().raw_face().data.as_ptr(),
+                   data.as_ptr());
+        assert_eq!(handler.get_face(*face).unwrap().is_italic(), info.italic);
+        let (_, coords, _) = handler.get_glyph(*face, 1, &mut atlases)
+            .unwrap().unwrap();
+        assert_eq!(atlases.placed[coords].width, width, "{:?}", info);
+    }
+}
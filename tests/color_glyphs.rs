@@ -0,0 +1,140 @@
+mod common;
+
+use std::sync::Arc;
+use common::{MemoryAtlases, TestFont, handler};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 128];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// The glyphs of `font`.
+struct Glyphs {
+    square: u16, bar: u16, dot: u16,
+    /// A color glyph: a square in palette entry 1, with a bar in the
+    /// foreground color on top.
+    flag: u16,
+    /// A color glyph: a dot in palette entry 0.
+    spot: u16,
+}
+
+/// A font with three plain glyphs and two color glyphs, and two palettes:
+/// red and green, then blue and white. With `tweak`, the font can be messed
+/// with before it's built.
+fn font(tweak: impl FnOnce(&mut TestFont)) -> (Arc<Vec<u8>>, Glyphs) {
+    let mut font = TestFont::new(1000);
+    let square = font.rect(100, 0, 700, 600);
+    let bar = font.rect(100, 200, 700, 400);
+    let dot = font.rect(300, 200, 500, 400);
+    let flag = font.rect(100, 0, 700, 600);
+    let spot = font.rect(300, 200, 500, 400);
+    // Added out of order, but they'll be sorted by glyph ID.
+    font.color_glyph(spot, &[(dot, 0)]);
+    font.color_glyph(flag, &[(square, 1), (bar, 0xFFFF)]);
+    font.palette(&[RED, GREEN]);
+    font.palette(&[BLUE, WHITE]);
+    tweak(&mut font);
+    (Arc::new(font.build()), Glyphs { square, bar, dot, flag, spot })
+}
+
+#[test]
+fn color_glyphs_decompose_into_layers() {
+    let (data, Glyphs { square, bar, dot, flag, spot }) = font(|_| ());
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    for glyph in [square, bar, dot] {
+        assert!(!handler.is_color_glyph(face, glyph));
+    }
+    for glyph in [flag, spot] {
+        assert!(handler.is_color_glyph(face, glyph));
+    }
+    let layers = handler.get_color_glyph(face, flag, 0, &mut atlases)
+        .unwrap().unwrap();
+    let summary: Vec<_> = layers.iter()
+        .map(|layer| (layer.glyph, layer.color)).collect();
+    assert_eq!(summary, vec![(square, Some(GREEN)), (bar, None)]);
+    // Each layer went into an atlas as an ordinary glyph.
+    for layer in layers.iter() {
+        let (atlas, coords, _) = handler.get_glyph(face, layer.glyph,
+                                                   &mut atlases)
+            .unwrap().unwrap();
+        assert_eq!((atlas, coords), (layer.atlas, layer.coords));
+    }
+    let layers = handler.get_color_glyph(face, spot, 0, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(layers.len(), 1);
+    assert_eq!((layers[0].glyph, layers[0].color), (dot, Some(RED)));
+}
+
+#[test]
+fn palettes_are_chosen_by_index() {
+    let (data, Glyphs { flag, spot, .. }) = font(|_| ());
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    let mut color = |glyph, palette| {
+        handler.get_color_glyph(face, glyph, palette, &mut atlases)
+            .unwrap().unwrap()[0].color
+    };
+    assert_eq!(color(flag, 1), Some(WHITE));
+    assert_eq!(color(spot, 1), Some(BLUE));
+    // There's no third palette, so this gets the first.
+    assert_eq!(color(flag, 2), Some(GREEN));
+    assert_eq!(color(spot, 0xFFFF), Some(RED));
+}
+
+#[test]
+fn plain_glyphs_are_not_color_glyphs() {
+    let (data, Glyphs { square, .. }) = font(|_| ());
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    assert!(handler.get_color_glyph(face, square, 0, &mut atlases)
+            .unwrap().is_none());
+    // Nor are glyphs the font doesn't have, nor any glyph of a font with no
+    // `COLR` table.
+    assert!(!handler.is_color_glyph(face, 100));
+    let mut plain = TestFont::new(1000);
+    let rect = plain.rect(100, 0, 500, 700);
+    let plain = handler.add_face(Arc::new(plain.build()), 0, 4.0, 32.0, 32.0)
+        .unwrap();
+    assert!(!handler.is_color_glyph(plain, rect));
+    assert!(handler.get_color_glyph(plain, rect, 0, &mut atlases)
+            .unwrap().is_none());
+}
+
+#[test]
+fn truncated_tables_are_not_trusted() {
+    let mut atlases = MemoryAtlases::new(256, 256);
+    let mut handler = handler();
+    let mut add = |tag, len| {
+        let (data, glyphs) = font(|font| font.truncate(tag, len));
+        (handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap(), glyphs)
+    };
+    // `COLR` without all of its header.
+    let (no_header, Glyphs { flag, .. }) = add(b"COLR", 10);
+    // The header (14 bytes), both base glyph records (6 each), and the
+    // first two layer records (4 each), which are `flag`'s. `spot` has a
+    // record, but its layer is gone.
+    let (no_spot, Glyphs { spot, .. }) = add(b"COLR", 34);
+    // The header of `CPAL` (12 bytes, and 4 of palette indices) and the
+    // first palette, but not the second.
+    let (one_palette, Glyphs { square, .. }) = add(b"CPAL", 24);
+    assert!(!handler.is_color_glyph(no_header, flag));
+    assert!(handler.get_color_glyph(no_header, flag, 0, &mut atlases)
+            .unwrap().is_none());
+    assert!(handler.is_color_glyph(no_spot, spot));
+    assert!(handler.get_color_glyph(no_spot, spot, 0, &mut atlases)
+            .unwrap().is_none());
+    let layers = handler.get_color_glyph(no_spot, flag, 0, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(layers[0].color, Some(GREEN));
+    let layers = handler.get_color_glyph(one_palette, flag, 0, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(layers[0].color, Some(GREEN));
+    // Colors that can't be found fall back to the foreground color.
+    let layers = handler.get_color_glyph(one_palette, flag, 1, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!((layers[0].glyph, layers[0].color), (square, None));
+}
@@ -0,0 +1,732 @@
+//! Shared bits for the integration tests: a tiny TrueType font builder (so we
+//! don't have to check binary fonts into the repository) and an
+//! `AtlasHandler` that just keeps everything in memory.
+
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+use psilo_text::{AtlasFormat, AtlasGrowth, AtlasHandler, GlyphInfo,
+                 TextHandler};
+
+/// A glyph's contours (each a list of on-curve points) and advance width.
+type TestGlyph = (Vec<Vec<(i16, i16)>>, u16);
+
+/// A glyph's image, in a `CBDT` table.
+#[derive(Clone)]
+pub enum Bitmap {
+    /// PNG data.
+    Png(Vec<u8>),
+    /// One byte per pixel, top row first.
+    Gray(Vec<u8>),
+}
+
+/// A bitmap: which glyph it's for, its strike's pixels per em, its left and
+/// top bearings, its width and height, and its image.
+type TestBitmap = (u16, u8, (i8, i8), (u8, u8), Bitmap);
+
+/// Builds a minimal TrueType (or CFF) font containing only straight-edged
+/// glyphs. Glyph 0 is always an empty `.notdef`.
+pub struct TestFont {
+    units_per_em: u16,
+    glyphs: Vec<TestGlyph>,
+    /// Whether to store the outlines in a `CFF ` table, rather than `glyf`.
+    cff: bool,
+    /// Tag, minimum, default and maximum of a variation axis, for `fvar`.
+    axis: Option<([u8; 4], f32, f32, f32)>,
+    /// How far each point of a glyph moves at the axis's maximum, for
+    /// `gvar`.
+    deltas: Vec<(u16, Vec<(i16, i16)>)>,
+    /// Color glyphs and their layers (glyph, palette entry), for `COLR`.
+    color_glyphs: Vec<(u16, Vec<(u16, u16)>)>,
+    /// RGBA palettes, for `CPAL`.
+    palettes: Vec<Vec<[u8; 4]>>,
+    /// Tables to cut short, and how long to leave them.
+    truncations: Vec<([u8; 4], usize)>,
+    /// Bitmaps, for `CBLC` and `CBDT`.
+    bitmaps: Vec<TestBitmap>,
+    /// Characters and their glyphs, for `cmap`.
+    chars: Vec<(char, u16)>,
+    /// Family and subfamily names, for `name`.
+    names: Option<(String, String)>,
+    /// Weight class and whether it's italic, for `OS/2`.
+    style: Option<(u16, bool)>,
+}
+
+impl TestFont {
+    pub fn new(units_per_em: u16) -> TestFont {
+        TestFont { units_per_em, glyphs: vec![(vec![], units_per_em / 2)],
+                   cff: false, axis: None, deltas: vec![], color_glyphs: vec![],
+                   palettes: vec![], truncations: vec![], bitmaps: vec![],
+                   chars: vec![], names: None, style: None }
+    }
+    /// Stores the outlines in a `CFF ` table, making this an OpenType font
+    /// with PostScript outlines, instead of in `glyf` and `loca`.
+    pub fn cff(&mut self) {
+        self.cff = true;
+    }
+    /// Makes this a variable font, with one axis.
+    pub fn axis(&mut self, tag: &[u8; 4], min: f32, default: f32, max: f32) {
+        self.axis = Some((*tag, min, default, max));
+    }
+    /// Makes the points of a glyph (in order, contour by contour) move by
+    /// the given amounts as the axis goes from its default to its maximum.
+    /// Only `glyf` outlines can vary.
+    pub fn deltas(&mut self, glyph: u16, deltas: &[(i16, i16)]) {
+        self.deltas.push((glyph, deltas.to_vec()));
+    }
+    /// Makes a glyph a color glyph, drawn as the given layers (each a glyph
+    /// and a palette entry, bottom-most first).
+    pub fn color_glyph(&mut self, glyph: u16, layers: &[(u16, u16)]) {
+        self.color_glyphs.push((glyph, layers.to_vec()));
+    }
+    /// Adds a palette of RGBA colors. Every palette should have as many
+    /// entries as the first.
+    pub fn palette(&mut self, colors: &[[u8; 4]]) {
+        self.palettes.push(colors.to_vec());
+    }
+    /// Gives a glyph a bitmap, in the strike with the given pixels per em.
+    /// `bearing` is from the origin to the top left corner of the image.
+    pub fn bitmap(&mut self, glyph: u16, ppem: u8, bearing: (i8, i8),
+                  size: (u8, u8), bitmap: Bitmap) {
+        self.bitmaps.push((glyph, ppem, bearing, size, bitmap));
+    }
+    /// Maps a character to a glyph.
+    pub fn map(&mut self, c: char, glyph: u16) {
+        self.chars.push((c, glyph));
+    }
+    /// Gives the face a family and subfamily name.
+    pub fn names(&mut self, family: &str, subfamily: &str) {
+        self.names = Some((family.to_string(), subfamily.to_string()));
+    }
+    /// Gives the face a weight class, and marks it as italic (or not).
+    pub fn style(&mut self, weight: u16, italic: bool) {
+        self.style = Some((weight, italic));
+    }
+    /// Cuts the given table short, leaving only its first `len` bytes.
+    pub fn truncate(&mut self, tag: &[u8; 4], len: usize) {
+        self.truncations.push((*tag, len));
+    }
+    /// Adds a glyph made of the given polygons, returning its glyph ID. An
+    /// empty list of contours makes an empty glyph (like a space).
+    pub fn glyph(&mut self, contours: &[&[(i16, i16)]], advance: u16) -> u16 {
+        self.glyphs.push((contours.iter().map(|x| x.to_vec()).collect(),
+                          advance));
+        (self.glyphs.len() - 1) as u16
+    }
+    /// Adds an axis-aligned rectangle glyph, wound clockwise like TrueType
+    /// outlines should be.
+    pub fn rect(&mut self, x_min: i16, y_min: i16, x_max: i16, y_max: i16)
+        -> u16 {
+        let advance = (x_max + x_min.max(0)) as u16;
+        self.glyph(&[&[(x_min, y_min), (x_min, y_max),
+                       (x_max, y_max), (x_max, y_min)]], advance)
+    }
+    /// Adds `count` rectangles, each a unit wider than the last, that come
+    /// out 30×30 texels at 32 texels per em with 4 texels of padding.
+    pub fn squares(&mut self, count: u16) -> Vec<u16> {
+        (0 .. count).map(|n| self.rect(0, 0, 800 + n as i16, 800)).collect()
+    }
+    /// Adds `count` rectangles of assorted sizes, like a real glyph mix:
+    /// tall, wide, small and large. Their sides are from 100 units long up
+    /// to (but not including) 100 + `spread` units.
+    pub fn assorted_rects(&mut self, count: u16, spread: (u16, u16))
+        -> Vec<u16> {
+        (0 .. count as u32).map(|n| {
+            let w = 100 + (n * 137) % spread.0 as u32;
+            let h = 100 + (n * 251) % spread.1 as u32;
+            self.rect(0, 0, w as i16, h as i16)
+        }).collect()
+    }
+    pub fn build(&self) -> Vec<u8> {
+        let mut glyf = vec![];
+        let mut loca = vec![];
+        let mut hmtx = vec![];
+        let (mut g_x_min, mut g_y_min, mut g_x_max, mut g_y_max)
+            = (0i16, 0i16, 0i16, 0i16);
+        for (contours, advance) in self.glyphs.iter() {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            let points = contours.iter().flatten();
+            let x_min = points.clone().map(|x| x.0).min().unwrap_or(0);
+            let y_min = points.clone().map(|x| x.1).min().unwrap_or(0);
+            let x_max = points.clone().map(|x| x.0).max().unwrap_or(0);
+            let y_max = points.clone().map(|x| x.1).max().unwrap_or(0);
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+            hmtx.extend_from_slice(&x_min.to_be_bytes());
+            if contours.is_empty() { continue }
+            g_x_min = g_x_min.min(x_min);
+            g_y_min = g_y_min.min(y_min);
+            g_x_max = g_x_max.max(x_max);
+            g_y_max = g_y_max.max(y_max);
+            push_i16s(&mut glyf, &[contours.len() as i16,
+                                   x_min, y_min, x_max, y_max]);
+            let mut end = 0;
+            for contour in contours.iter() {
+                end += contour.len();
+                push_i16s(&mut glyf, &[end as i16 - 1]);
+            }
+            push_i16s(&mut glyf, &[0]); // no instructions
+            // Every point is on-curve, with two-byte coordinate deltas.
+            glyf.extend(points.clone().map(|_| 0x01u8));
+            let mut last = 0;
+            for &(x, _) in points.clone() {
+                push_i16s(&mut glyf, &[x - last]);
+                last = x;
+            }
+            let mut last = 0;
+            for &(_, y) in points {
+                push_i16s(&mut glyf, &[y - last]);
+                last = y;
+            }
+            while glyf.len() % 4 != 0 { glyf.push(0) }
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+        let num_glyphs = self.glyphs.len() as u16;
+        let mut head = vec![];
+        head.extend_from_slice(&0x00010000u32.to_be_bytes()); // version
+        head.extend_from_slice(&0x00010000u32.to_be_bytes()); // revision
+        head.extend_from_slice(&0u32.to_be_bytes()); // checksum adjustment
+        head.extend_from_slice(&0x5F0F3CF5u32.to_be_bytes()); // magic
+        push_i16s(&mut head, &[0, self.units_per_em as i16]);
+        head.extend_from_slice(&[0; 16]); // created, modified
+        push_i16s(&mut head, &[g_x_min, g_y_min, g_x_max, g_y_max,
+                               0, 8, 2, 1, 0]);
+        let mut hhea = vec![];
+        hhea.extend_from_slice(&0x00010000u32.to_be_bytes());
+        push_i16s(&mut hhea, &[g_y_max, g_y_min, 0,
+                               self.units_per_em as i16, 0, 0, g_x_max,
+                               1, 0, 0, 0, 0, 0, 0, 0,
+                               num_glyphs as i16]);
+        let mut maxp = vec![];
+        maxp.extend_from_slice(&0x00005000u32.to_be_bytes());
+        push_i16s(&mut maxp, &[num_glyphs as i16]);
+        let mut tables = vec![
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"hmtx", hmtx),
+            (*b"maxp", maxp),
+        ];
+        if self.cff {
+            tables.push((*b"CFF ", build_cff(&self.glyphs)));
+        }
+        else {
+            tables.push((*b"glyf", glyf));
+            tables.push((*b"loca", loca));
+        }
+        if let Some((tag, min, default, max)) = self.axis {
+            // No named instances, and no name for the axis.
+            let mut fvar = vec![];
+            fvar.extend_from_slice(&0x00010000u32.to_be_bytes());
+            push_i16s(&mut fvar, &[16, 2, 1, 20, 0, 8]);
+            fvar.extend_from_slice(&tag);
+            for value in [min, default, max] {
+                fvar.extend_from_slice(&((value * 65536.0) as i32)
+                                       .to_be_bytes());
+            }
+            push_i16s(&mut fvar, &[0, 256]);
+            tables.push((*b"fvar", fvar));
+            tables.push((*b"gvar", build_gvar(&self.glyphs, &self.deltas)));
+        }
+        if !self.color_glyphs.is_empty() {
+            tables.push((*b"COLR", build_colr(&self.color_glyphs)));
+        }
+        if !self.palettes.is_empty() {
+            tables.push((*b"CPAL", build_cpal(&self.palettes)));
+        }
+        if !self.bitmaps.is_empty() {
+            let (cblc, cbdt) = build_cbdt(&self.bitmaps);
+            tables.push((*b"CBLC", cblc));
+            tables.push((*b"CBDT", cbdt));
+        }
+        if !self.chars.is_empty() {
+            tables.push((*b"cmap", build_cmap(&self.chars)));
+        }
+        if let Some((family, subfamily)) = &self.names {
+            tables.push((*b"name", build_name(&[(1, family),
+                                                (2, subfamily)])));
+        }
+        if let Some((weight, italic)) = self.style {
+            // Version 0, which is just long enough for the selection flags.
+            let mut os2 = vec![0; 78];
+            os2[4 .. 6].copy_from_slice(&weight.to_be_bytes());
+            os2[6 .. 8].copy_from_slice(&5u16.to_be_bytes()); // normal width
+            os2[62 .. 64].copy_from_slice(&(italic as u16).to_be_bytes());
+            tables.push((*b"OS/2", os2));
+        }
+        for (tag, len) in self.truncations.iter() {
+            let (_, table) = tables.iter_mut().find(|(x, _)| x == tag)
+                .expect("truncating a table that isn't there");
+            table.truncate(*len);
+        }
+        let version = if self.cff { u32::from_be_bytes(*b"OTTO") }
+                      else { 0x00010000 };
+        build_sfnt(version, &mut tables)
+    }
+}
+
+/// Builds a `gvar` table for one axis, with a tuple for each glyph that has
+/// deltas, peaking at the axis's maximum.
+fn build_gvar(glyphs: &[TestGlyph], deltas: &[(u16, Vec<(i16, i16)>)])
+    -> Vec<u8> {
+    let mut data = vec![];
+    let mut offsets = vec![];
+    for (glyph, (contours, _)) in glyphs.iter().enumerate() {
+        offsets.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let Some((_, moves)) = deltas.iter()
+            .find(|(n, _)| *n as usize == glyph) else { continue };
+        // Every point has a delta, including the four phantom points
+        // after the outline, which stay put.
+        let points = contours.iter().map(Vec::len).sum();
+        assert_eq!(moves.len(), points, "glyph {}", glyph);
+        let padded: Vec<(i16, i16)> = moves.iter().copied()
+            .chain([(0, 0); 4]).collect();
+        let mut serialized = vec![0]; // all points
+        for axis in [0, 1] {
+            // Runs of up to 64 two-byte deltas.
+            for run in padded.chunks(64) {
+                serialized.push(0x40 | (run.len() as u8 - 1));
+                for delta in run {
+                    let delta = if axis == 0 { delta.0 } else { delta.1 };
+                    push_i16s(&mut serialized, &[delta]);
+                }
+            }
+        }
+        // One tuple, with its peak (1.0) embedded and its own point
+        // numbers.
+        push_i16s(&mut data, &[1, 10, serialized.len() as i16,
+                               0xA000u16 as i16, 0x4000]);
+        data.extend(serialized);
+    }
+    offsets.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let mut gvar = vec![];
+    gvar.extend_from_slice(&0x00010000u32.to_be_bytes());
+    push_i16s(&mut gvar, &[1, 0]);
+    let data_at = 20 + offsets.len() as u32;
+    gvar.extend_from_slice(&data_at.to_be_bytes()); // no shared tuples
+    push_i16s(&mut gvar, &[glyphs.len() as i16, 1]);
+    gvar.extend_from_slice(&data_at.to_be_bytes());
+    gvar.extend(offsets);
+    gvar.extend(data);
+    gvar
+}
+
+/// Builds a `cmap` table with a single format 12 subtable, with a group for
+/// each character.
+fn build_cmap(chars: &[(char, u16)]) -> Vec<u8> {
+    let mut chars = chars.to_vec();
+    chars.sort();
+    let mut cmap = vec![];
+    push_i16s(&mut cmap, &[0, 1, 3, 10]);
+    cmap.extend_from_slice(&12u32.to_be_bytes());
+    push_i16s(&mut cmap, &[12, 0]);
+    cmap.extend_from_slice(&(16 + 12 * chars.len() as u32).to_be_bytes());
+    cmap.extend_from_slice(&0u32.to_be_bytes()); // language
+    cmap.extend_from_slice(&(chars.len() as u32).to_be_bytes());
+    for (c, glyph) in chars {
+        cmap.extend_from_slice(&(c as u32).to_be_bytes());
+        cmap.extend_from_slice(&(c as u32).to_be_bytes());
+        cmap.extend_from_slice(&(glyph as u32).to_be_bytes());
+    }
+    cmap
+}
+
+/// Builds a version 0 `name` table, with each name (ID and string) in
+/// Windows Unicode, for US English.
+fn build_name(names: &[(u16, &str)]) -> Vec<u8> {
+    let mut records = vec![];
+    let mut storage = vec![];
+    for &(id, name) in names {
+        let utf16: Vec<u8> = name.encode_utf16()
+            .flat_map(u16::to_be_bytes).collect();
+        push_i16s(&mut records, &[3, 1, 0x409, id as i16,
+                                  utf16.len() as i16, storage.len() as i16]);
+        storage.extend(utf16);
+    }
+    let mut name = vec![];
+    push_i16s(&mut name, &[0, names.len() as i16,
+                           6 + records.len() as i16]);
+    name.extend(records);
+    name.extend(storage);
+    name
+}
+
+/// Builds a version 0 `COLR` table.
+fn build_colr(color_glyphs: &[(u16, Vec<(u16, u16)>)]) -> Vec<u8> {
+    let mut color_glyphs = color_glyphs.to_vec();
+    color_glyphs.sort_by_key(|(glyph, _)| *glyph);
+    let mut base_glyphs = vec![];
+    let mut layers = vec![];
+    for (glyph, glyph_layers) in color_glyphs.iter() {
+        push_i16s(&mut base_glyphs, &[*glyph as i16, (layers.len() / 4) as i16,
+                                      glyph_layers.len() as i16]);
+        for &(layer, entry) in glyph_layers {
+            push_i16s(&mut layers, &[layer as i16, entry as i16]);
+        }
+    }
+    let mut colr = vec![];
+    push_i16s(&mut colr, &[0, color_glyphs.len() as i16]);
+    colr.extend_from_slice(&14u32.to_be_bytes());
+    colr.extend_from_slice(&(14 + base_glyphs.len() as u32).to_be_bytes());
+    push_i16s(&mut colr, &[(layers.len() / 4) as i16]);
+    colr.extend(base_glyphs);
+    colr.extend(layers);
+    colr
+}
+
+/// Builds a version 0 `CPAL` table, with the palettes one after another.
+fn build_cpal(palettes: &[Vec<[u8; 4]>]) -> Vec<u8> {
+    let entries = palettes[0].len();
+    let mut cpal = vec![];
+    push_i16s(&mut cpal, &[0, entries as i16, palettes.len() as i16,
+                           (entries * palettes.len()) as i16]);
+    let records_at = 12 + palettes.len() * 2;
+    cpal.extend_from_slice(&(records_at as u32).to_be_bytes());
+    for n in 0 .. palettes.len() {
+        push_i16s(&mut cpal, &[(n * entries) as i16]);
+    }
+    for &[r, g, b, a] in palettes.iter().flatten() {
+        cpal.extend_from_slice(&[b, g, r, a]);
+    }
+    cpal
+}
+
+/// Builds the `CBLC` and `CBDT` tables: a strike for each size, where each
+/// glyph with a bitmap has an index subtable of its own, with small metrics.
+fn build_cbdt(bitmaps: &[TestBitmap]) -> (Vec<u8>, Vec<u8>) {
+    let mut bitmaps = bitmaps.to_vec();
+    bitmaps.sort_by_key(|&(glyph, ppem, ..)| (ppem, glyph));
+    let strikes: Vec<&[TestBitmap]> = bitmaps.chunk_by(|a, b| a.1 == b.1)
+        .collect();
+    let mut cbdt = 0x00030000u32.to_be_bytes().to_vec();
+    let mut sizes = vec![];
+    let mut index = vec![];
+    for strike in strikes.iter() {
+        let array_at = 8 + strikes.len() * 48 + index.len();
+        // Index subtable array entries (8 bytes each), then the subtables
+        // they point to (16 bytes each).
+        let mut array = vec![];
+        let mut subtables = vec![];
+        for (glyph, _, bearing, size, bitmap) in strike.iter() {
+            push_i16s(&mut array, &[*glyph as i16, *glyph as i16]);
+            let at = strike.len() * 8 + subtables.len();
+            array.extend_from_slice(&(at as u32).to_be_bytes());
+            let start = cbdt.len();
+            cbdt.extend_from_slice(&[size.1, size.0, bearing.0 as u8,
+                                     bearing.1 as u8, size.0]);
+            let image_format = match bitmap {
+                Bitmap::Png(data) => {
+                    cbdt.extend_from_slice(&(data.len() as u32)
+                                           .to_be_bytes());
+                    cbdt.extend_from_slice(data);
+                    17
+                },
+                Bitmap::Gray(data) => {
+                    cbdt.extend_from_slice(data);
+                    1
+                },
+            };
+            push_i16s(&mut subtables, &[1, image_format]);
+            for offset in [start, 0, cbdt.len() - start] {
+                subtables.extend_from_slice(&(offset as u32).to_be_bytes());
+            }
+        }
+        let gray = strike.iter()
+            .any(|(.., bitmap)| matches!(bitmap, Bitmap::Gray(_)));
+        let ppem = strike[0].1;
+        sizes.extend_from_slice(&(array_at as u32).to_be_bytes());
+        sizes.extend_from_slice(&((array.len() + subtables.len()) as u32)
+                                .to_be_bytes());
+        sizes.extend_from_slice(&(strike.len() as u32).to_be_bytes());
+        sizes.extend_from_slice(&[0; 28]); // color, line metrics
+        push_i16s(&mut sizes, &[strike[0].0 as i16,
+                                strike[strike.len() - 1].0 as i16]);
+        sizes.extend_from_slice(&[ppem, ppem, if gray { 8 } else { 32 }, 1]);
+        index.extend(array);
+        index.extend(subtables);
+    }
+    let mut cblc = 0x00030000u32.to_be_bytes().to_vec();
+    cblc.extend_from_slice(&(strikes.len() as u32).to_be_bytes());
+    cblc.extend(sizes);
+    cblc.extend(index);
+    (cblc, cbdt)
+}
+
+/// Builds a bare-bones `CFF ` table: no private dictionary, no subroutines,
+/// and each glyph's contours drawn with nothing but `rmoveto` and
+/// `rlineto`.
+fn build_cff(glyphs: &[TestGlyph]) -> Vec<u8> {
+    let char_strings: Vec<Vec<u8>> = glyphs.iter().map(|(contours, _)| {
+        let mut char_string = vec![];
+        let mut last = (0, 0);
+        for contour in contours {
+            for (n, &(x, y)) in contour.iter().enumerate() {
+                for delta in [x - last.0, y - last.1] {
+                    char_string.push(28); // a 16-bit number follows
+                    char_string.extend_from_slice(&delta.to_be_bytes());
+                }
+                char_string.push(if n == 0 { 21 } else { 5 });
+                last = (x, y);
+            }
+        }
+        char_string.push(14); // endchar
+        char_string
+    }).collect();
+    let header = [1, 0, 4, 4];
+    let name = cff_index(&[b"Test".to_vec()]);
+    // The only thing in the top dictionary is where the charstrings are,
+    // as a 32-bit number. That makes it 6 bytes long, and its index 17.
+    let char_strings_at = header.len() + name.len() + 17 + 2 + 2;
+    let mut top_dict = vec![29];
+    top_dict.extend_from_slice(&(char_strings_at as u32).to_be_bytes());
+    top_dict.push(17); // CharStrings
+    let mut out = header.to_vec();
+    out.extend(name);
+    out.extend(cff_index(&[top_dict]));
+    out.extend(cff_index(&[])); // strings
+    out.extend(cff_index(&[])); // global subroutines
+    assert_eq!(out.len(), char_strings_at);
+    out.extend(cff_index(&char_strings));
+    out
+}
+
+/// Makes a CFF `INDEX` of the given items, with four byte offsets.
+fn cff_index(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = vec![];
+    push_i16s(&mut out, &[items.len() as i16]);
+    if items.is_empty() { return out }
+    out.push(4);
+    let mut offset = 1u32;
+    out.extend_from_slice(&offset.to_be_bytes());
+    for item in items {
+        offset += item.len() as u32;
+        out.extend_from_slice(&offset.to_be_bytes());
+    }
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+fn push_i16s(out: &mut Vec<u8>, values: &[i16]) {
+    for value in values {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Wraps some tables up into an sfnt. Checksums are left as zero, since
+/// nothing we test against checks them.
+fn build_sfnt(version: u32, tables: &mut [([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    tables.sort_by_key(|x| x.0);
+    let mut out = vec![];
+    out.extend_from_slice(&version.to_be_bytes());
+    push_i16s(&mut out, &[tables.len() as i16, 0, 0, 0]);
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in tables.iter() {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += (data.len() + 3) & !3;
+    }
+    for (_, data) in tables.iter() {
+        out.extend_from_slice(data);
+        while out.len() % 4 != 0 { out.push(0) }
+    }
+    out
+}
+
+/// Wraps some fonts (made by `TestFont::build`) up into a TrueType
+/// collection.
+pub fn build_collection(fonts: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = b"ttcf".to_vec();
+    out.extend_from_slice(&0x00010000u32.to_be_bytes());
+    out.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+    let mut offset = 12 + 4 * fonts.len();
+    for font in fonts {
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        offset += font.len();
+    }
+    for font in fonts {
+        // Table offsets are from the start of the file, so they move along
+        // with the font.
+        let base = out.len() as u32;
+        let mut font = font.clone();
+        let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for table in 0 .. num_tables {
+            let at = 12 + table * 16 + 8;
+            let offset = u32::from_be_bytes(font[at .. at + 4].try_into()
+                                            .unwrap());
+            font[at .. at + 4].copy_from_slice(&(offset + base).to_be_bytes());
+        }
+        out.extend(font);
+    }
+    out
+}
+
+/// One glyph, as it was handed to `add_to_atlas`.
+#[derive(Clone, Debug)]
+pub struct Placed {
+    pub atlas: usize,
+    pub render_bounds: (f32, f32, f32, f32),
+    pub x: u32, pub y: u32,
+    pub width: u32, pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// An `AtlasHandler` that remembers everything it's told, and uses the index
+/// of the glyph in `placed` as its `AtlasCoords`.
+pub struct MemoryAtlases {
+    /// Size of atlases in groups that aren't in `group_sizes`.
+    pub size: (u32, u32),
+    pub group_sizes: HashMap<usize, (u32, u32)>,
+    /// How big atlases may grow. `None` means they don't.
+    pub max_size: Option<(u32, u32)>,
+    /// Group and format of each atlas.
+    pub atlases: Vec<(usize, AtlasFormat)>,
+    /// Current size of each atlas.
+    pub sizes: Vec<(u32, u32)>,
+    /// Texture array layer of each atlas, if it was made with
+    /// `new_atlas_layer`.
+    pub layers: Vec<Option<u32>>,
+    pub placed: Vec<Placed>,
+    /// Every `AtlasCoords` that `rederive_coords` was called for.
+    pub rederived: Vec<usize>,
+}
+
+impl MemoryAtlases {
+    pub fn new(width: u32, height: u32) -> MemoryAtlases {
+        MemoryAtlases { size: (width, height), group_sizes: HashMap::new(),
+                        max_size: None, atlases: vec![], sizes: vec![],
+                        layers: vec![], placed: vec![], rederived: vec![] }
+    }
+}
+
+impl AtlasHandler for MemoryAtlases {
+    type AtlasID = usize;
+    type AtlasCoords = usize;
+    type E = ();
+    fn new_atlas(&mut self, group: usize, format: AtlasFormat)
+        -> Result<usize, ()> {
+        self.atlases.push((group, format));
+        let size = self.get_atlas_size(group);
+        self.sizes.push(size);
+        self.layers.push(None);
+        Ok(self.atlases.len() - 1)
+    }
+    fn new_atlas_layer(&mut self, group: usize, format: AtlasFormat,
+                       layer: u32, width: u32, height: u32)
+        -> Result<usize, ()> {
+        let previous = self.atlases.iter().zip(self.layers.iter())
+            .filter(|(x, layer)| **x == (group, format) && layer.is_some())
+            .count();
+        assert_eq!(layer as usize, previous);
+        self.atlases.push((group, format));
+        self.sizes.push((width, height));
+        self.layers.push(Some(layer));
+        Ok(self.atlases.len() - 1)
+    }
+    fn get_atlas_size(&mut self, group: usize) -> (u32, u32) {
+        self.group_sizes.get(&group).copied().unwrap_or(self.size)
+    }
+    fn growth(&mut self) -> Option<&mut dyn AtlasGrowth<usize, ()>> {
+        if self.max_size.is_some() { Some(self) } else { None }
+    }
+    fn rederive_coords(&mut self, _atlas: usize, coords: usize,
+                       _info: &GlyphInfo) -> Result<usize, ()> {
+        self.rederived.push(coords);
+        Ok(coords)
+    }
+    fn add_to_atlas(&mut self, target_atlas: usize,
+                    render_x_min: f32, render_y_min: f32,
+                    render_x_max: f32, render_y_max: f32,
+                    glyph_x: u32, glyph_y: u32,
+                    glyph_width: u32, glyph_height: u32,
+                    glyph_pixels: &[u8]) -> Result<usize, ()> {
+        let (_, format) = self.atlases[target_atlas];
+        let size = self.sizes[target_atlas];
+        assert!(glyph_x + glyph_width <= size.0);
+        assert!(glyph_y + glyph_height <= size.1);
+        assert_eq!(glyph_pixels.len(),
+                   (glyph_width * glyph_height) as usize
+                   * format.bytes_per_texel());
+        self.placed.push(Placed {
+            atlas: target_atlas,
+            render_bounds: (render_x_min, render_y_min,
+                            render_x_max, render_y_max),
+            x: glyph_x, y: glyph_y,
+            width: glyph_width, height: glyph_height,
+            pixels: glyph_pixels.to_vec(),
+        });
+        Ok(self.placed.len() - 1)
+    }
+}
+
+impl AtlasGrowth<usize, ()> for MemoryAtlases {
+    fn get_max_atlas_size(&mut self, group: usize) -> (u32, u32) {
+        self.max_size.unwrap_or_else(|| self.get_atlas_size(group))
+    }
+    fn resize_atlas(&mut self, atlas: usize, new_width: u32, new_height: u32)
+        -> Result<(), ()> {
+        let (old_width, old_height) = self.sizes[atlas];
+        assert!(new_width >= old_width && new_height >= old_height);
+        self.sizes[atlas] = (new_width, new_height);
+        // Layers of an array all grow together.
+        if self.layers[atlas].is_some() {
+            for other in 0 .. self.atlases.len() {
+                if self.atlases[other] == self.atlases[atlas]
+                && self.layers[other].is_some() {
+                    self.sizes[other] = (new_width, new_height);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A font with `count` glyphs that are 30×30 texels at 32 texels per em
+/// (see [`TestFont::squares`]).
+pub fn square_font(count: u16) -> (Arc<Vec<u8>>, Vec<u16>) {
+    let mut font = TestFont::new(1000);
+    let glyphs = font.squares(count);
+    (Arc::new(font.build()), glyphs)
+}
+
+/// A font with `count` glyphs of assorted sizes (see
+/// [`TestFont::assorted_rects`]).
+pub fn assorted_font(count: u16, spread: (u16, u16))
+    -> (Arc<Vec<u8>>, Vec<u16>) {
+    let mut font = TestFont::new(1000);
+    let glyphs = font.assorted_rects(count, spread);
+    (Arc::new(font.build()), glyphs)
+}
+
+/// A `TextHandler` that renders in the foreground, so that glyphs come back
+/// the first time they're asked for.
+pub fn handler() -> TextHandler<usize, usize> {
+    #[allow(unused_mut)]
+    let mut handler = TextHandler::new();
+    #[cfg(feature="bg-render")]
+    handler.set_render_in_background(false);
+    handler
+}
+
+/// Returns true if the texel under the given point, in ems, is inside.
+pub fn inside_at(placed: &Placed, x: f32, y: f32) -> bool {
+    let (x_min, y_min, x_max, y_max) = placed.render_bounds;
+    let x = (x - x_min) / (x_max - x_min) * placed.width as f32;
+    let y = (y - y_min) / (y_max - y_min) * placed.height as f32;
+    msdf_inside(placed, x as u32, y as u32)
+}
+
+/// Returns true if the MSDF (or MTSDF) texel at the given position is inside
+/// the shape.
+pub fn msdf_inside(placed: &Placed, x: u32, y: u32) -> bool {
+    let bytes_per_texel = placed.pixels.len()
+        / (placed.width * placed.height) as usize;
+    let i = (y * placed.width + x) as usize * bytes_per_texel;
+    let mut texel = [placed.pixels[i], placed.pixels[i+1], placed.pixels[i+2]];
+    texel.sort();
+    texel[1] > 127
+}
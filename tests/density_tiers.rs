@@ -0,0 +1,61 @@
+mod common;
+
+use std::sync::Arc;
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font whose only glyph is a 500 unit square. With 4 texels of padding,
+/// it's 12 texels wide at 16 texels per em, 20 at 32, and 36 at 64.
+fn font() -> Arc<Vec<u8>> {
+    let mut font = TestFont::new(1000);
+    font.rect(0, 0, 500, 500);
+    Arc::new(font.build())
+}
+
+#[test]
+fn tiers_are_picked_by_size() {
+    let mut handler = handler();
+    let face = handler.add_face(font(), 0, 4.0, 32.0, 32.0).unwrap();
+    assert_eq!(handler.get_density_tiers(face), Some(&[1.0][..]));
+    assert_eq!(handler.set_density_tiers(face, &[2.0, f32::NAN, 0.5, -1.0]),
+               Some(()));
+    assert_eq!(handler.get_density_tiers(face), Some(&[0.5, 1.0, 2.0][..]));
+    assert_eq!(handler.tier_for_size(face, 10.0), Some(0.5));
+    assert_eq!(handler.tier_for_size(face, 16.0), Some(0.5));
+    assert_eq!(handler.tier_for_size(face, 20.0), Some(1.0));
+    assert_eq!(handler.tier_for_size(face, 50.0), Some(2.0));
+    assert_eq!(handler.tier_for_size(face, 500.0), Some(2.0));
+    assert_eq!(handler.set_density_tiers(face + 1, &[2.0]), None);
+}
+
+#[test]
+fn each_tier_is_rendered_and_cached_separately() {
+    let mut handler = handler();
+    let face = handler.add_face(font(), 0, 4.0, 32.0, 32.0).unwrap();
+    handler.set_density_tiers(face, &[0.5, 2.0]).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let (_, big, _) = handler.get_glyph_for_size(face, 1, 50.0, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(atlases.placed[big].width, 36);
+    // Metrics don't care which tier the glyph was asked for in.
+    assert!(handler.get_glyph_metrics(face, 1).is_some());
+    assert!(handler.get_glyph_info(face, 1).is_none());
+    let (_, small, _) = handler.get_glyph_for_size(face, 1, 12.0, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(atlases.placed[small].width, 12);
+    let (_, normal, _) = handler.get_glyph(face, 1, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(atlases.placed[normal].width, 20);
+    // Asking again hits the cache.
+    handler.get_glyph_for_size(face, 1, 60.0, &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.placed.len(), 3);
+    let mut tiers: Vec<_> = handler.cached_glyphs().map(|x| x.tier)
+        .collect();
+    tiers.sort_by(f32::total_cmp);
+    assert_eq!(tiers, vec![0.5, 1.0, 2.0]);
+    // The padding is in texels, so it covers less of an em at higher
+    // densities.
+    let big = handler.get_glyph_info_for_size(face, 1, 50.0).unwrap();
+    assert!((big.render_x_min + 2.0 / 64.0).abs() < 1e-4, "{:?}", big);
+    let normal = handler.get_glyph_info(face, 1).unwrap();
+    assert!((normal.render_x_min + 2.0 / 32.0).abs() < 1e-4, "{:?}", normal);
+}
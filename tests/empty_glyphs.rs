@@ -0,0 +1,56 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::GlyphMetrics;
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font with a space, a glyph whose outline is a line (no area), and an
+/// ordinary glyph.
+fn font() -> (Arc<Vec<u8>>, u16, u16, u16) {
+    let mut font = TestFont::new(1000);
+    let space = font.glyph(&[], 250);
+    let line = font.glyph(&[&[(100, 0), (100, 700)]], 300);
+    let rect = font.rect(100, 0, 500, 700);
+    (Arc::new(font.build()), space, line, rect)
+}
+
+#[test]
+fn empty_glyphs_have_metrics_but_no_atlas_space() {
+    let (data, space, line, rect) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    assert!(handler.get_glyph(face, space, &mut atlases).unwrap().is_none());
+    assert_eq!(handler.get_glyph_metrics(face, space),
+               Some(GlyphMetrics { advance: 0.25, left_side_bearing: 0.0,
+                                   right_side_bearing: 0.25 }));
+    assert!(handler.get_glyph(face, line, &mut atlases).unwrap().is_none());
+    assert_eq!(handler.get_glyph_metrics(face, line),
+               Some(GlyphMetrics { advance: 0.3, left_side_bearing: 0.1,
+                                   right_side_bearing: 0.2 }));
+    assert!(atlases.atlases.is_empty());
+    assert!(handler.get_glyph(face, rect, &mut atlases).unwrap().is_some());
+    assert_eq!(handler.get_glyph_metrics(face, rect),
+               Some(GlyphMetrics { advance: 0.6, left_side_bearing: 0.1,
+                                   right_side_bearing: 0.1 }));
+    // Glyphs that aren't in the font at all have no metrics.
+    assert!(handler.get_glyph(face, 1234, &mut atlases).unwrap().is_none());
+    assert_eq!(handler.get_glyph_metrics(face, 1234), None);
+}
+
+#[cfg(feature="bg-render")]
+#[test]
+fn empty_glyphs_finish_background_rendering() {
+    let (data, space, _, _) = font();
+    let mut handler: psilo_text::TextHandler<usize, usize>
+        = psilo_text::TextHandler::new();
+    let face = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    for _ in 0 .. 1000 {
+        assert!(handler.get_glyph(face, space, &mut atlases).unwrap()
+                .is_none());
+        if handler.get_glyph_metrics(face, space).is_some() { return }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    panic!("empty glyph never finished rendering in the background");
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{ErrorCorrection, MsdfConfig};
+use common::{MemoryAtlases, Placed, TestFont, handler, msdf_inside};
+
+/// A font with a star, whose acute points are where MSDFs tend to clash.
+fn font() -> (Arc<Vec<u8>>, u16) {
+    let mut font = TestFont::new(1000);
+    let star = font.glyph(&[&[(500, 900), (560, 450), (900, 400), (560, 350),
+                              (500, -100), (440, 350), (100, 400),
+                              (440, 450)]], 1000);
+    (Arc::new(font.build()), star)
+}
+
+fn render(mode: ErrorCorrection) -> Placed {
+    let (data, star) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 16.0, 16.0).unwrap();
+    handler.set_msdf_config(face, MsdfConfig {
+        error_correction: mode,
+        ..MsdfConfig::default()
+    }).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let (_, coords, _) = handler.get_glyph(face, star, &mut atlases)
+        .unwrap().unwrap();
+    atlases.placed.swap_remove(coords)
+}
+
+#[test]
+fn correction_never_moves_texels_across_the_outline() {
+    let off = render(ErrorCorrection::Off);
+    for mode in [ErrorCorrection::EdgePriority, ErrorCorrection::Full] {
+        let corrected = render(mode);
+        assert_eq!((corrected.width, corrected.height),
+                   (off.width, off.height));
+        for y in 0 .. off.height {
+            for x in 0 .. off.width {
+                assert_eq!(msdf_inside(&corrected, x, y),
+                           msdf_inside(&off, x, y),
+                           "{:?} flipped texel {},{}", mode, x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn corrected_texels_are_flattened() {
+    let off = render(ErrorCorrection::Off);
+    let full = render(ErrorCorrection::Full);
+    let changed: Vec<_> = off.pixels.chunks(3).zip(full.pixels.chunks(3))
+        .filter(|(a, b)| a != b).map(|(_, b)| b).collect();
+    assert!(!changed.is_empty(), "no clashes found in a star");
+    for texel in changed {
+        assert!(texel[0] == texel[1] && texel[1] == texel[2], "{:?}", texel);
+    }
+}
@@ -0,0 +1,85 @@
+//! These are meant to be run under Miri, too, to check that the faces we
+//! hand out never outlive the data they borrow from.
+
+mod common;
+
+use std::sync::Arc;
+use psilo_text::TextHandler;
+use rustybuzz::Variation;
+use ttf_parser::{GlyphId, Tag};
+use common::{MemoryAtlases, TestFont, handler};
+
+fn wght(value: f32) -> Variation {
+    Variation { tag: Tag::from_bytes(b"wght"), value }
+}
+
+/// A rectangle whose right edge moves from 500 units out to 700 as the
+/// weight goes from 400 to 900.
+fn variable_font() -> (Vec<u8>, u16) {
+    let mut font = TestFont::new(1000);
+    font.axis(b"wght", 100.0, 400.0, 900.0);
+    let rect = font.rect(100, 0, 500, 700);
+    font.deltas(rect, &[(0, 0), (0, 0), (200, 0), (200, 0)]);
+    (font.build(), rect)
+}
+
+fn x_max(handler: &TextHandler<usize, usize>, face: usize, glyph: u16)
+    -> i16 {
+    handler.get_face(face).unwrap().glyph_bounding_box(GlyphId(glyph))
+        .unwrap().x_max
+}
+
+#[test]
+fn faces_live_as_long_as_their_data() {
+    let mut handler = handler();
+    let (data, rect) = variable_font();
+    // The handler is the only owner of the data from here on.
+    let a = handler.add_face(data, 0, 4.0, 8.0, 8.0).unwrap();
+    let b = handler.add_face_variant(a, Default::default()).unwrap();
+    handler.remove_face(a).unwrap();
+    assert!(handler.get_face(a).is_none());
+    // `b` still has the data.
+    assert_eq!(x_max(&handler, b, rect), 500);
+    let face = handler.get_face(b).unwrap().clone();
+    assert_eq!(face.units_per_em(), 1000);
+    drop(face);
+    // Replacing it drops the old data, along with the face parsed from it.
+    let mut font = TestFont::new(2048);
+    font.rect(0, 0, 300, 300);
+    handler.replace_face_data(b, Arc::new(font.build())).unwrap();
+    assert_eq!(handler.get_face(b).unwrap().units_per_em(), 2048);
+    assert_eq!(x_max(&handler, b, 1), 300);
+    drop(handler);
+}
+
+#[test]
+fn variations_can_be_changed() {
+    let mut handler = handler();
+    let (data, rect) = variable_font();
+    let data = Arc::new(data);
+    let regular = handler.add_face_with_variations(data.clone(), 0,
+                                                   &[wght(400.0)], 4.0,
+                                                   8.0, 8.0).unwrap();
+    let other = handler.add_face(data.clone(), 0, 4.0, 8.0, 8.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    let (_, before, _) = handler.get_glyph(regular, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(x_max(&handler, regular, rect), 500);
+    handler.set_face_variations(regular, &[wght(900.0)]).unwrap();
+    assert_eq!(x_max(&handler, regular, rect), 700);
+    // Faces sharing the data keep their own variations.
+    assert_eq!(x_max(&handler, other, rect), 500);
+    // The glyph is rendered again, from the new outline.
+    let (_, after, _) = handler.get_glyph(regular, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_ne!(before, after);
+    assert!(atlases.placed[after].width > atlases.placed[before].width);
+    // The new variations stick when the data is replaced...
+    handler.replace_face_data(regular, Arc::new(variable_font().0))
+        .unwrap();
+    assert_eq!(x_max(&handler, regular, rect), 700);
+    // ...and unmentioned axes go back to their defaults.
+    handler.set_face_variations(regular, &[]).unwrap();
+    assert_eq!(x_max(&handler, regular, rect), 500);
+    assert!(handler.set_face_variations(regular + 100, &[]).is_none());
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{FillRule, MsdfConfig};
+use common::{MemoryAtlases, Placed, TestFont, handler, inside_at};
+
+/// A glyph, a point inside it only under nonzero winding, and a point inside
+/// it under both rules.
+type Case<T> = (T, (i16, i16), (i16, i16));
+
+/// Glyphs whose insides depend on the fill rule, with TrueType or CFF
+/// outlines. Points are in font units.
+fn font(cff: bool) -> (Arc<Vec<u8>>, Vec<Case<u16>>) {
+    let mut font = TestFont::new(1000);
+    if cff { font.cff() }
+    // Two rectangles wound the same way, overlapping in the middle.
+    let overlap = font.glyph(&[&[(100, 0), (100, 600), (500, 600), (500, 0)],
+                               &[(300, 200), (300, 800), (700, 800),
+                                 (700, 200)]], 800);
+    // A "hole" wound the same way as its outline, as some converted fonts
+    // have.
+    let same_way_hole = font.glyph(&[&[(100, 0), (100, 800), (700, 800),
+                                       (700, 0)],
+                                     &[(300, 200), (300, 600), (500, 600),
+                                       (500, 200)]], 800);
+    // A pentagram, drawn as a single self-intersecting contour. The pentagon
+    // in the middle is wound around twice.
+    let pentagram = font.glyph(&[&[(500, 900), (735, 90), (45, 590),
+                                   (955, 590), (265, 90)]], 1000);
+    (Arc::new(font.build()), vec![
+        (overlap, (400, 400), (200, 100)),
+        (same_way_hole, (400, 400), (200, 100)),
+        (pentagram, (500, 450), (500, 800)),
+    ])
+}
+
+/// Returns true if the texel under the given point (in font units) is
+/// inside.
+fn inside(placed: &Placed, (x, y): (i16, i16)) -> bool {
+    inside_at(placed, x as f32 / 1000.0, y as f32 / 1000.0)
+}
+
+fn render(fill_rule: Option<FillRule>) -> Vec<Case<Placed>> {
+    let (data, glyphs) = font(false);
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    if let Some(fill_rule) = fill_rule {
+        handler.set_msdf_config(face, MsdfConfig {
+            fill_rule,
+            ..MsdfConfig::default()
+        }).unwrap();
+    }
+    let mut atlases = MemoryAtlases::new(256, 256);
+    glyphs.into_iter().map(|(glyph, differs, same)| {
+        let (_, coords, _) = handler.get_glyph(face, glyph, &mut atlases)
+            .unwrap().unwrap();
+        (atlases.placed[coords].clone(), differs, same)
+    }).collect()
+}
+
+#[test]
+fn truetype_faces_default_to_nonzero() {
+    for (n, (placed, differs, same)) in render(None).iter().enumerate() {
+        assert!(inside(placed, *differs), "glyph {} has a hole", n);
+        assert!(inside(placed, *same), "glyph {} is empty", n);
+    }
+}
+
+#[test]
+fn nonzero_fills_overlaps() {
+    for (n, (placed, differs, same)) in render(Some(FillRule::NonZero))
+        .iter().enumerate() {
+        assert!(inside(placed, *differs), "glyph {} has a hole", n);
+        assert!(inside(placed, *same), "glyph {} is empty", n);
+    }
+}
+
+#[test]
+fn even_odd_hollows_overlaps() {
+    for (n, (placed, differs, same)) in render(Some(FillRule::EvenOdd))
+        .iter().enumerate() {
+        assert!(!inside(placed, *differs), "glyph {} has no hole", n);
+        assert!(inside(placed, *same), "glyph {} is empty", n);
+    }
+}
+
+#[test]
+fn cff_faces_default_to_nonzero() {
+    let (data, glyphs) = font(true);
+    let mut handler = handler();
+    let face = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    assert_eq!(handler.get_msdf_config(face).unwrap().fill_rule,
+               FillRule::NonZero);
+    // Unless we say otherwise.
+    let even_odd = handler.add_face_with_fill_rule(data, 0, FillRule::EvenOdd,
+                                                   4.0, 32.0, 32.0).unwrap();
+    assert_eq!(handler.get_msdf_config(even_odd).unwrap().fill_rule,
+               FillRule::EvenOdd);
+    let mut atlases = MemoryAtlases::new(256, 256);
+    for (n, (glyph, differs, same)) in glyphs.into_iter().enumerate() {
+        let (_, coords, _) = handler.get_glyph(face, glyph, &mut atlases)
+            .unwrap().unwrap();
+        let placed = &atlases.placed[coords];
+        assert!(inside(placed, differs), "glyph {} has a hole", n);
+        assert!(inside(placed, same), "glyph {} is empty", n);
+        let (_, coords, _) = handler.get_glyph(even_odd, glyph, &mut atlases)
+            .unwrap().unwrap();
+        let placed = &atlases.placed[coords];
+        assert!(!inside(placed, differs), "glyph {} has no hole", n);
+        assert!(inside(placed, same), "glyph {} is empty", n);
+    }
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{FontData, TextHandler};
+use common::{MemoryAtlases, TestFont};
+
+fn font() -> Vec<u8> {
+    let mut font = TestFont::new(1000);
+    font.rect(0, 0, 500, 500);
+    font.build()
+}
+
+/// Renders glyph 1 of the given face, waiting for the background renderer
+/// if need be.
+fn render(handler: &mut TextHandler<usize, usize>,
+          atlases: &mut MemoryAtlases, face: usize) -> u32 {
+    loop {
+        if let Some((_, coords, _)) = handler.get_glyph(face, 1, atlases)
+            .unwrap() {
+            return atlases.placed[coords].width
+        }
+        std::thread::yield_now();
+    }
+}
+
+/// Writes the font to a file of its own, returning its path.
+fn font_file(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir()
+        .join(format!("psilo-text-test-{}-{}.ttf", name,
+                      std::process::id()));
+    std::fs::write(&path, font()).unwrap();
+    path
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // (Miri can't map files.)
+fn faces_can_be_added_from_paths() {
+    let path = font_file("path");
+    let mut handler = TextHandler::new();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    // SAFETY: Nothing else knows about this file, and we only delete it,
+    // which leaves the mapping alone.
+    let face = unsafe { handler.add_face_from_path(&path, 0, 4.0, 32.0,
+                                                   32.0) }
+        .unwrap().unwrap();
+    // There's no second face in there.
+    assert!(unsafe { handler.add_face_from_path(&path, 1, 4.0, 32.0, 32.0) }
+            .unwrap().is_none());
+    // The mapping outlives the file.
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(render(&mut handler, &mut atlases, face), 20);
+    assert!(unsafe { handler.add_face_from_path(&path, 0, 4.0, 32.0, 32.0) }
+            .is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // (Miri can't map files.)
+fn faces_can_be_mapped_from_files() {
+    let path = font_file("map");
+    // SAFETY: Nothing else knows about this file, and we only delete it,
+    // which leaves the mapping alone.
+    let data = unsafe { FontData::map_file(&path) }.unwrap();
+    assert_eq!(data.bytes(), &font()[..]);
+    std::fs::remove_file(&path).unwrap();
+    // Still mapped, so still usable. (Renders in the background, if that's
+    // enabled, from the same map.)
+    let mut handler = TextHandler::new();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    assert_eq!(render(&mut handler, &mut atlases, face), 20);
+    assert!(unsafe { FontData::map_file(&path) }.is_err());
+}
+
+#[test]
+fn faces_can_be_added_from_any_kind_of_data() {
+    let mut handler = TextHandler::new();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let leaked: &'static [u8] = Box::leak(font().into_boxed_slice());
+    let slice: Arc<[u8]> = font().into();
+    let faces = [
+        handler.add_face(leaked, 0, 4.0, 32.0, 32.0).unwrap(),
+        handler.add_face(slice, 0, 4.0, 32.0, 32.0).unwrap(),
+        handler.add_face(font(), 0, 4.0, 32.0, 32.0).unwrap(),
+        handler.add_face(Arc::new(font()), 0, 4.0, 32.0, 32.0).unwrap(),
+    ];
+    for face in faces {
+        assert_eq!(render(&mut handler, &mut atlases, face), 20);
+    }
+}
+
+#[test]
+fn clones_share_their_bytes() {
+    let data = FontData::from(font());
+    let clone = data.clone();
+    assert!(data.ptr_eq(&clone));
+    assert_eq!(data.bytes().as_ptr(), clone.bytes().as_ptr());
+    // Equal, but not the same.
+    assert!(!data.ptr_eq(&FontData::from(font())));
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{MemoryAtlases, handler, square_font};
+
+#[test]
+fn full_atlases_grow_before_new_ones_are_made() {
+    let (data, glyphs) = square_font(10);
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 32);
+    atlases.max_size = Some((128, 64));
+    let mut ids = vec![];
+    for &glyph in glyphs.iter() {
+        let (atlas, _, _) = handler.get_glyph(face, glyph, &mut atlases)
+            .unwrap().unwrap();
+        ids.push(atlas);
+    }
+    // Two glyphs fit at first. Then the atlas doubles in height to fit
+    // four, and in width to fit eight. Then it's as big as it gets, and
+    // the rest go into a new atlas.
+    assert_eq!(ids, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+    assert_eq!(atlases.sizes, vec![(128, 64), (64, 32)]);
+    // Every glyph in an atlas when it grew got new coords (including the
+    // ones that made it grow).
+    let mut rederived = atlases.rederived.clone();
+    rederived.sort();
+    rederived.dedup();
+    assert_eq!(rederived, vec![0, 1, 2, 3, 4]);
+    // Nothing overlaps.
+    for (i, a) in atlases.placed.iter().enumerate() {
+        for b in atlases.placed[i+1 ..].iter() {
+            assert!(a.atlas != b.atlas
+                    || a.x + a.width <= b.x || b.x + b.width <= a.x
+                    || a.y + a.height <= b.y || b.y + b.height <= a.y);
+        }
+    }
+}
+
+#[test]
+fn atlases_do_not_grow_by_default() {
+    let (data, glyphs) = square_font(10);
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 32);
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    assert_eq!(atlases.sizes, vec![(64, 32); 5]);
+    assert!(atlases.rederived.is_empty());
+}
+
+#[test]
+fn glyphs_bigger_than_a_fresh_atlas_grow_it() {
+    let (data, glyphs) = square_font(10);
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(16, 16);
+    atlases.max_size = Some((64, 64));
+    handler.get_glyph(face, glyphs[0], &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.sizes, vec![(32, 32)]);
+    // It wasn't shrunk to fit the initial size.
+    assert_eq!(atlases.placed[0].width, 30);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::MsdfConfig;
+use common::{MemoryAtlases, TestFont, handler};
+
+fn font() -> (Arc<Vec<u8>>, u16) {
+    let mut font = TestFont::new(1000);
+    let triangle = font.glyph(&[&[(100, 0), (500, 800), (900, 0)]], 1000);
+    (Arc::new(font.build()), triangle)
+}
+
+#[test]
+fn each_config_is_cached_separately() {
+    let (data, glyph) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let (_, first, _) = handler.get_glyph(face, glyph, &mut atlases)
+        .unwrap().unwrap();
+    let seeded = MsdfConfig { seed: 1234, ..MsdfConfig::default() };
+    handler.set_msdf_config(face, seeded).unwrap();
+    let (_, second, _) = handler.get_glyph(face, glyph, &mut atlases)
+        .unwrap().unwrap();
+    assert_ne!(first, second);
+    assert_eq!(handler.cached_glyphs().count(), 2);
+    // Switching back finds the first one still there.
+    handler.set_msdf_config(face, MsdfConfig::default()).unwrap();
+    let (_, again, _) = handler.get_glyph(face, glyph, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(again, first);
+    assert_eq!(atlases.placed.len(), 2);
+}
+
+#[test]
+fn corner_angle_threshold_is_clamped() {
+    let (data, glyph) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let right_angle = MsdfConfig {
+        corner_angle_threshold: std::f64::consts::FRAC_PI_2,
+        ..MsdfConfig::default()
+    };
+    handler.set_msdf_config(face, right_angle).unwrap();
+    let (_, first, _) = handler.get_glyph(face, glyph, &mut atlases)
+        .unwrap().unwrap();
+    // Anything past a right angle is the same as a right angle, and gets
+    // the same glyph.
+    handler.set_msdf_config(face, MsdfConfig {
+        corner_angle_threshold: 3.0,
+        ..right_angle
+    }).unwrap();
+    let (_, second, _) = handler.get_glyph(face, glyph, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(first, second);
+    assert_eq!(atlases.placed.len(), 1);
+}
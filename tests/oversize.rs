@@ -0,0 +1,92 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{AtlasFormat, Error, OversizeGlyphs, Rect, TextHandler};
+use common::{MemoryAtlases, TestFont, msdf_inside};
+
+/// A font with one ordinary glyph and one glyph four ems across, which won't
+/// fit in a 128×128 atlas at 64 texels per em.
+fn font() -> (Arc<Vec<u8>>, u16, u16) {
+    let mut font = TestFont::new(1000);
+    let small = font.rect(100, 0, 500, 700);
+    let huge = font.rect(0, -1000, 4000, 3000);
+    (Arc::new(font.build()), small, huge)
+}
+
+fn handler(oversize: OversizeGlyphs) -> TextHandler<usize, usize> {
+    let mut handler = common::handler();
+    handler.set_oversize_glyphs(oversize);
+    handler
+}
+
+#[test]
+fn shrinks_oversize_glyphs() {
+    let (data, small, huge) = font();
+    let mut handler = handler(OversizeGlyphs::Shrink);
+    let face = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let (_, coords, format) = handler.get_glyph(face, huge, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(format, AtlasFormat::Msdf);
+    let placed = &atlases.placed[coords];
+    assert!(placed.width <= 128 && placed.height <= 128);
+    // It should fill the atlas along its longest dimension, instead of being
+    // cropped or squashed.
+    assert_eq!(placed.width, 128);
+    assert_eq!(placed.height, 128);
+    // The render bounds still describe the whole glyph, plus the border.
+    let (x_min, y_min, x_max, y_max) = placed.render_bounds;
+    let border_ems = 2.0 * 4.0 / 124.0;
+    assert!((x_min - (0.0 - border_ems)).abs() < 0.001, "{}", x_min);
+    assert!((y_min - (-1.0 - border_ems)).abs() < 0.001, "{}", y_min);
+    assert!((x_max - (4.0 + border_ems)).abs() < 0.001, "{}", x_max);
+    assert!((y_max - (3.0 + border_ems)).abs() < 0.001, "{}", y_max);
+    // The glyph info agrees, and the distance range in ems reflects the
+    // reduced density.
+    let info = handler.get_glyph_info(face, huge).unwrap();
+    assert_eq!(info.atlas_rect, Rect { x: placed.x, y: placed.y,
+                                       w: 128, h: 128 });
+    assert_eq!(info.render_x_min, x_min);
+    assert_eq!(info.distance_range_texels, 4.0);
+    assert!((info.distance_range_ems_x - 2.0 * border_ems).abs() < 0.001);
+    // The glyph is solid, so the middle is inside and the corners aren't.
+    assert!(msdf_inside(placed, 64, 64));
+    assert!(!msdf_inside(placed, 0, 0));
+    assert!(!msdf_inside(placed, 127, 127));
+    // Glyphs that fit are rendered at full density.
+    let (_, coords, _) = handler.get_glyph(face, small, &mut atlases)
+        .unwrap().unwrap();
+    let placed = &atlases.placed[coords];
+    assert_eq!((placed.width, placed.height), (30, 49));
+}
+
+#[test]
+fn rejects_oversize_glyphs() {
+    let (data, small, huge) = font();
+    let mut handler = handler(OversizeGlyphs::Reject);
+    let face = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    match handler.get_glyph(face, huge, &mut atlases) {
+        Err(Error::GlyphTooLarge { face: f, glyph, width, height }) => {
+            assert_eq!((f, glyph), (face, huge));
+            assert_eq!((width, height), (260, 260));
+        },
+        Err(x) => panic!("wrong error: {:?}", x),
+        Ok(_) => panic!("oversize glyph wasn't rejected"),
+    }
+    assert!(atlases.placed.is_empty());
+    // The rejection is remembered, rather than retried every time.
+    assert!(matches!(handler.get_glyph(face, huge, &mut atlases), Ok(None)));
+    // Glyphs that fit are unaffected.
+    assert!(handler.get_glyph(face, small, &mut atlases).unwrap().is_some());
+}
+
+#[test]
+fn rejects_when_even_the_border_does_not_fit() {
+    let (data, small, _) = font();
+    let mut handler = handler(OversizeGlyphs::Shrink);
+    let face = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(4, 4);
+    assert!(matches!(handler.get_glyph(face, small, &mut atlases),
+                     Err(Error::GlyphTooLarge { .. })));
+}
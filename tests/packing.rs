@@ -0,0 +1,99 @@
+mod common;
+
+use psilo_text::{AtlasPacker, Packing, SkylinePacker, TextHandler};
+use common::{MemoryAtlases, assorted_font, handler};
+
+fn fill(packing: Packing, max_size: Option<(u32, u32)>)
+    -> (TextHandler<usize, usize>, MemoryAtlases) {
+    let (data, glyphs) = assorted_font(40, (700, 800));
+    let mut handler = handler();
+    handler.set_packing(packing);
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    atlases.max_size = max_size;
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    (handler, atlases)
+}
+
+fn assert_no_overlaps(atlases: &MemoryAtlases) {
+    for (i, a) in atlases.placed.iter().enumerate() {
+        for b in atlases.placed[i+1 ..].iter() {
+            assert!(a.atlas != b.atlas
+                    || a.x + a.width <= b.x || b.x + b.width <= a.x
+                    || a.y + a.height <= b.y || b.y + b.height <= a.y,
+                    "{:?} overlaps {:?}", (a.x, a.y, a.width, a.height),
+                    (b.x, b.y, b.width, b.height));
+        }
+    }
+}
+
+#[test]
+fn every_packer_packs_without_overlaps() {
+    for packing in [Packing::Skyline, Packing::MaxRects, Packing::Shelf] {
+        for max_size in [None, Some((256, 128))] {
+            // (MemoryAtlases checks that everything is in bounds.)
+            let (handler, atlases) = fill(packing, max_size);
+            assert_no_overlaps(&atlases);
+            let occupancy = handler.get_atlas_occupancy();
+            assert_eq!(occupancy.len(), atlases.atlases.len());
+            let glyphs: usize = occupancy.iter().map(|x| x.glyphs).sum();
+            assert_eq!(glyphs, atlases.placed.len());
+            for (n, atlas) in occupancy.iter().enumerate() {
+                assert_eq!((atlas.width, atlas.height), atlases.sizes[n]);
+                let used: u64 = atlases.placed.iter()
+                    .filter(|x| x.atlas == n)
+                    .map(|x| x.width as u64 * x.height as u64).sum();
+                assert_eq!(atlas.used_texels, used);
+                assert!(atlas.fraction() > 0.0 && atlas.fraction() <= 1.0);
+            }
+        }
+    }
+}
+
+#[test]
+fn max_rects_packs_at_least_as_tightly_as_shelves() {
+    let (_, shelf) = fill(Packing::Shelf, None);
+    let (_, max_rects) = fill(Packing::MaxRects, None);
+    assert!(max_rects.atlases.len() <= shelf.atlases.len(),
+            "MaxRects used {} atlases, shelves used {}",
+            max_rects.atlases.len(), shelf.atlases.len());
+}
+
+#[test]
+fn custom_packers_are_used() {
+    /// Puts everything in one row along the bottom.
+    struct OneRow { w: u32, h: u32, used: u32 }
+    impl AtlasPacker for OneRow {
+        fn size(&self) -> (u32, u32) { (self.w, self.h) }
+        fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+            if h > self.h || w > self.w - self.used { return None }
+            self.used += w;
+            Some((self.used - w, 0))
+        }
+        fn resize(&mut self, w: u32, h: u32) { self.w = w; self.h = h }
+    }
+    fn one_row(w: u32, h: u32) -> Box<dyn AtlasPacker> {
+        Box::new(OneRow { w, h, used: 0 })
+    }
+    let (_, atlases) = fill(Packing::Custom(one_row), None);
+    assert!(atlases.placed.iter().all(|x| x.y == 0));
+    assert_no_overlaps(&atlases);
+}
+
+#[test]
+fn skyline_comes_back_down_where_rectangles_are_freed() {
+    let mut packer = SkylinePacker::new(100, 100);
+    assert_eq!(packer.pack(40, 30), Some((0, 0)));
+    assert_eq!(packer.pack(60, 20), Some((40, 0)));
+    assert_eq!(packer.pack(20, 50), Some((40, 20)));
+    // Nothing is on top of the first, so its space comes back.
+    packer.free(0, 0, 40, 30);
+    assert_eq!(packer.pack(40, 70), Some((0, 0)));
+    // The second has something on part of it. The rest comes back.
+    packer.free(40, 0, 60, 20);
+    assert_eq!(packer.pack(40, 90), Some((60, 0)));
+    assert_eq!(packer.pack(60, 31), None);
+    assert_eq!(packer.pack(60, 30), Some((0, 70)));
+}
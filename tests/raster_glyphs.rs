@@ -0,0 +1,95 @@
+mod common;
+
+use std::{io::Cursor, sync::Arc};
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+use psilo_text::AtlasFormat;
+use common::{Bitmap, MemoryAtlases, TestFont, handler};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+/// A PNG with its top half red and its bottom half blue.
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbaImage::from_fn(width, height, |_, y| {
+        Rgba(if y < height / 2 { RED } else { BLUE })
+    });
+    let mut png = vec![];
+    image.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    png
+}
+
+/// The glyphs of `font`, none of which have outlines.
+struct Glyphs {
+    /// A 4×2 PNG at 32 pixels per em, and an 8×4 one at 64.
+    emoji: u16,
+    /// An uncompressed grayscale bitmap, which we don't support.
+    gray: u16,
+    /// A "PNG" that isn't one.
+    broken: u16,
+    /// No image at all.
+    blank: u16,
+}
+
+fn font() -> (Arc<Vec<u8>>, Glyphs) {
+    let mut font = TestFont::new(1000);
+    let emoji = font.glyph(&[], 1000);
+    let gray = font.glyph(&[], 1000);
+    let broken = font.glyph(&[], 1000);
+    let blank = font.glyph(&[], 1000);
+    font.bitmap(emoji, 32, (2, 6), (4, 2), Bitmap::Png(png(4, 2)));
+    font.bitmap(emoji, 64, (4, 12), (8, 4), Bitmap::Png(png(8, 4)));
+    font.bitmap(gray, 32, (2, 6), (4, 2), Bitmap::Gray(vec![128; 8]));
+    font.bitmap(broken, 32, (2, 6), (4, 2),
+                Bitmap::Png(b"not a PNG".to_vec()));
+    (Arc::new(font.build()), Glyphs { emoji, gray, broken, blank })
+}
+
+#[test]
+fn png_glyphs_go_into_bitmap_atlases() {
+    let (data, Glyphs { emoji, .. }) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    let (atlas, coords, _) = handler.get_glyph(face, emoji, &mut atlases)
+        .unwrap().unwrap();
+    assert_eq!(atlases.atlases[atlas].1, AtlasFormat::Bitmap);
+    let placed = &atlases.placed[coords];
+    assert_eq!((placed.width, placed.height), (4, 2));
+    // Flipped to go bottom to top, like everything else in an atlas.
+    assert_eq!(&placed.pixels[.. 4], &BLUE);
+    assert_eq!(&placed.pixels[16 .. 20], &RED);
+    // The image's size and bearings, in ems.
+    assert_eq!(placed.render_bounds, (2.0 / 32.0, 4.0 / 32.0,
+                                      6.0 / 32.0, 6.0 / 32.0));
+}
+
+#[test]
+fn the_closest_strike_is_used() {
+    let (data, Glyphs { emoji, .. }) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    let (_, coords, _) = handler.get_glyph(face, emoji, &mut atlases)
+        .unwrap().unwrap();
+    let placed = &atlases.placed[coords];
+    assert_eq!((placed.width, placed.height), (8, 4));
+    // Same place, same size, in ems.
+    assert_eq!(placed.render_bounds, (2.0 / 32.0, 4.0 / 32.0,
+                                      6.0 / 32.0, 6.0 / 32.0));
+}
+
+#[test]
+fn images_we_cant_decode_are_missing() {
+    let (data, Glyphs { gray, broken, blank, .. }) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    for glyph in [gray, broken] {
+        assert!(handler.get_glyph(face, glyph, &mut atlases).unwrap()
+                .is_none());
+    }
+    // As is a glyph with neither an outline nor an image.
+    assert!(handler.get_glyph(face, blank, &mut atlases).unwrap().is_none());
+    assert!(atlases.placed.is_empty());
+}
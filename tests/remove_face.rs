@@ -0,0 +1,132 @@
+mod common;
+
+use psilo_text::{Error, Packing};
+use common::{MemoryAtlases, handler, square_font};
+
+#[test]
+fn removed_faces_keep_other_indices_stable() {
+    let (data, glyphs) = square_font(4);
+    let mut handler = handler();
+    let a = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let b = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    handler.get_glyph(b, glyphs[0], &mut atlases).unwrap().unwrap();
+    assert_eq!(handler.remove_face(a), Some(()));
+    assert_eq!(handler.remove_face(a), None);
+    assert_eq!(handler.get_render_params(a), None);
+    // `b` still works, and its glyph is still cached.
+    assert!(handler.get_face(b).is_some());
+    handler.get_glyph(b, glyphs[0], &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.placed.len(), 1);
+    // `a`'s index isn't handed out again.
+    let c = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    assert!(c != a && c != b);
+}
+
+#[test]
+fn removed_faces_are_invalid_everywhere() {
+    let (data, glyphs) = square_font(4);
+    let mut handler = handler();
+    let a = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    handler.get_glyph(a, glyphs[0], &mut atlases).unwrap().unwrap();
+    handler.remove_face(a).unwrap();
+    let g = glyphs[0];
+    assert_eq!(handler.tier_for_size(a, 32.0), None);
+    assert!(!handler.is_color_glyph(a, g));
+    assert!(handler.get_glyph_metrics(a, g).is_none());
+    assert!(handler.get_glyph_info(a, g).is_none());
+    assert!(handler.get_glyph_info_for_size(a, g, 32.0).is_none());
+    assert!(matches!(handler.get_glyph(a, g, &mut atlases),
+                     Err(Error::NoSuchFace { face }) if face == a));
+    assert!(matches!(handler.get_glyph_for_size(a, g, 32.0, &mut atlases),
+                     Err(Error::NoSuchFace { face }) if face == a));
+    assert!(matches!(handler.get_color_glyph(a, g, 0, &mut atlases),
+                     Err(Error::NoSuchFace { face }) if face == a));
+}
+
+#[test]
+fn removed_glyphs_leave_room_for_others() {
+    let (data, glyphs) = square_font(4);
+    let mut handler = handler();
+    let a = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(a, glyph, &mut atlases).unwrap().unwrap();
+    }
+    assert_eq!(handler.stats().atlases[0].glyphs, 4);
+    handler.remove_face(a).unwrap();
+    let occupancy = handler.get_atlas_occupancy();
+    assert_eq!((occupancy[0].glyphs, occupancy[0].used_texels), (0, 0));
+    assert_eq!(handler.cached_glyphs().count(), 0);
+    // The atlas is empty, so another face's glyphs fill it all over again.
+    let b = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(b, glyph, &mut atlases).unwrap().unwrap();
+    }
+    assert_eq!(atlases.atlases.len(), 1);
+}
+
+#[test]
+fn the_default_packer_reuses_single_freed_glyphs() {
+    let (data, glyphs) = square_font(4);
+    let mut handler = handler();
+    let a = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let b = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    handler.get_glyph(b, glyphs[0], &mut atlases).unwrap().unwrap();
+    handler.get_glyph(b, glyphs[1], &mut atlases).unwrap().unwrap();
+    handler.get_glyph(b, glyphs[2], &mut atlases).unwrap().unwrap();
+    // (The skyline can only come back down where nothing is on top, so
+    // free one from the top row.)
+    handler.get_glyph(a, glyphs[3], &mut atlases).unwrap().unwrap();
+    let freed = (atlases.placed[3].x, atlases.placed[3].y);
+    handler.remove_face(a).unwrap();
+    assert_eq!(handler.stats().atlases[0].glyphs, 3);
+    let c = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    handler.get_glyph(c, glyphs[3], &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.atlases.len(), 1);
+    let placed = atlases.placed.last().unwrap();
+    assert_eq!((placed.atlas, placed.x, placed.y), (0, freed.0, freed.1));
+}
+
+#[test]
+fn max_rects_reuses_single_freed_glyphs() {
+    let (data, glyphs) = square_font(4);
+    let mut handler = handler();
+    handler.set_packing(Packing::MaxRects);
+    let a = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let b = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    handler.get_glyph(a, glyphs[0], &mut atlases).unwrap().unwrap();
+    handler.get_glyph(b, glyphs[1], &mut atlases).unwrap().unwrap();
+    handler.get_glyph(b, glyphs[2], &mut atlases).unwrap().unwrap();
+    handler.get_glyph(b, glyphs[3], &mut atlases).unwrap().unwrap();
+    let freed = (atlases.placed[0].x, atlases.placed[0].y);
+    handler.remove_face(a).unwrap();
+    let c = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    handler.get_glyph(c, glyphs[0], &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.atlases.len(), 1);
+    let placed = atlases.placed.last().unwrap();
+    assert_eq!((placed.atlas, placed.x, placed.y), (0, freed.0, freed.1));
+}
+
+#[cfg(feature="bg-render")]
+#[test]
+fn removing_a_face_cancels_its_background_renders() {
+    let (data, glyphs) = square_font(4);
+    let mut handler = psilo_text::TextHandler::new();
+    let a = handler.add_face(data.clone(), 0, 4.0, 32.0, 32.0).unwrap();
+    let b = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    for &glyph in glyphs.iter() {
+        assert!(handler.get_glyph(a, glyph, &mut atlases).unwrap().is_none());
+    }
+    handler.remove_face(a).unwrap();
+    // Whatever of `a` was already rendered is quietly thrown away.
+    while handler.get_glyph(b, glyphs[0], &mut atlases).unwrap().is_none() {
+        std::thread::yield_now();
+    }
+    assert_eq!(atlases.placed.len(), 1);
+    assert!(handler.cached_glyphs().all(|x| x.face == b));
+}
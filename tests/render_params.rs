@@ -0,0 +1,54 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::RenderParams;
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font with one rectangle, 400×700 units.
+fn font() -> (Arc<Vec<u8>>, u16) {
+    let mut font = TestFont::new(1000);
+    let rect = font.rect(100, 0, 500, 700);
+    (Arc::new(font.build()), rect)
+}
+
+#[test]
+fn border_sets_range_and_padding_together() {
+    let params = RenderParams::from_border(4.0, 64.0, 32.0);
+    assert_eq!(params, RenderParams {
+        distance_range: 4.0, padding: 2.0,
+        texels_per_em_x: 64.0, texels_per_em_y: 32.0,
+    });
+    let (data, _) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 64.0, 32.0).unwrap();
+    assert_eq!(handler.get_render_params(face), Some(params));
+}
+
+#[test]
+fn range_and_padding_are_independent() {
+    let (data, rect) = font();
+    let mut handler = handler();
+    let wide_range = handler.add_face_with_params(data.clone(), 0, &[],
+        RenderParams { distance_range: 16.0, padding: 1.0,
+                       texels_per_em_x: 100.0, texels_per_em_y: 100.0 })
+        .unwrap();
+    let wide_padding = handler.add_face_with_params(data, 0, &[],
+        RenderParams { distance_range: 2.0, padding: 10.0,
+                       texels_per_em_x: 100.0, texels_per_em_y: 100.0 })
+        .unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    handler.get_glyph(wide_range, rect, &mut atlases).unwrap().unwrap();
+    handler.get_glyph(wide_padding, rect, &mut atlases).unwrap().unwrap();
+    let info = handler.get_glyph_info(wide_range, rect).unwrap();
+    assert_eq!((info.atlas_rect.w, info.atlas_rect.h), (42, 72));
+    assert_eq!(info.distance_range_texels, 16.0);
+    assert!((info.render_x_min - 0.09).abs() < 0.001, "{}", info.render_x_min);
+    let info = handler.get_glyph_info(wide_padding, rect).unwrap();
+    assert_eq!((info.atlas_rect.w, info.atlas_rect.h), (60, 90));
+    assert_eq!(info.distance_range_texels, 2.0);
+    assert!((info.render_x_min - 0.0).abs() < 0.001, "{}", info.render_x_min);
+    // A small range saturates quickly: the corner of the padding is well
+    // over a range away from the outline, so every channel reads "outside".
+    let placed = &atlases.placed[1];
+    assert_eq!(&placed.pixels[0 .. 3], &[0, 0, 0]);
+}
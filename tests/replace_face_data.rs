@@ -0,0 +1,59 @@
+mod common;
+
+use std::sync::Arc;
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font whose only glyph is a square of the given size. At 32 texels per
+/// em, with padding, a 500 unit square is 20 texels wide, and an 800 unit
+/// square is 30.
+fn font(size: i16) -> Arc<Vec<u8>> {
+    let mut font = TestFont::new(1000);
+    font.rect(0, 0, size, size);
+    Arc::new(font.build())
+}
+
+#[test]
+fn replaced_faces_render_from_the_new_data() {
+    let mut handler = handler();
+    let face = handler.add_face(font(500), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    handler.get_glyph(face, 1, &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.placed[0].width, 20);
+    assert_eq!(handler.replace_face_data(face, font(800)), Some(()));
+    assert!(handler.get_glyph_info(face, 1).is_none());
+    assert_eq!(handler.get_atlas_occupancy()[0].glyphs, 0);
+    let (_, coords, _) = handler.get_glyph(face, 1, &mut atlases).unwrap()
+        .unwrap();
+    assert_eq!(atlases.placed[coords].width, 30);
+    assert_eq!(handler.get_glyph_info(face, 1).unwrap().atlas_rect.w, 30);
+    assert_eq!(handler.get_face(face).unwrap().glyph_bounding_box(
+        ttf_parser::GlyphId(1)).unwrap().x_max, 800);
+}
+
+#[test]
+fn bad_data_leaves_the_face_alone() {
+    let mut handler = handler();
+    let face = handler.add_face(font(500), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    handler.get_glyph(face, 1, &mut atlases).unwrap().unwrap();
+    assert_eq!(handler.replace_face_data(face, Arc::new(vec![0; 16])), None);
+    assert_eq!(handler.replace_face_data(face + 1, font(800)), None);
+    // Still cached, so nothing new is rendered.
+    handler.get_glyph(face, 1, &mut atlases).unwrap().unwrap();
+    assert_eq!(atlases.placed.len(), 1);
+}
+
+#[cfg(feature="bg-render")]
+#[test]
+fn stale_background_renders_are_thrown_away() {
+    let mut handler = psilo_text::TextHandler::new();
+    let face = handler.add_face(font(500), 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    assert!(handler.get_glyph(face, 1, &mut atlases).unwrap().is_none());
+    handler.replace_face_data(face, font(800)).unwrap();
+    while handler.get_glyph(face, 1, &mut atlases).unwrap().is_none() {
+        std::thread::yield_now();
+    }
+    assert_eq!(atlases.placed.len(), 1);
+    assert_eq!(atlases.placed[0].width, 30);
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::RenderParams;
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font with two 500 unit squares. They're 20 texels wide at 32 texels
+/// per em with 4 texels of padding, and 36 at 64.
+fn font() -> (Arc<Vec<u8>>, Vec<u16>) {
+    let mut font = TestFont::new(1000);
+    let glyphs = vec![font.rect(0, 0, 500, 500), font.rect(0, 0, 500, 499)];
+    (Arc::new(font.build()), glyphs)
+}
+
+fn sharper() -> RenderParams {
+    RenderParams::from_border(4.0, 64.0, 64.0)
+}
+
+#[test]
+fn new_params_rerender_cached_glyphs() {
+    let (data, glyphs) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    for &glyph in glyphs.iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    assert_eq!(handler.set_render_params(face, sharper()), Some(()));
+    assert_eq!(handler.get_render_params(face), Some(sharper()));
+    assert!(handler.is_rerendering(face));
+    // In the foreground, it's all done by the next `get_glyph`.
+    let (_, coords, _) = handler.get_glyph(face, glyphs[0], &mut atlases)
+        .unwrap().unwrap();
+    assert!(!handler.is_rerendering(face));
+    assert_eq!(atlases.placed[coords].width, 36);
+    assert_eq!(handler.get_glyph_info(face, glyphs[1]).unwrap().atlas_rect.w,
+               36);
+    // The old glyphs' space was freed.
+    let occupancy = handler.get_atlas_occupancy();
+    assert_eq!(occupancy[0].glyphs, 2);
+    assert_eq!(occupancy[0].used_texels, 2 * 36 * 36);
+    assert_eq!(handler.set_render_params(face + 1, sharper()), None);
+}
+
+#[test]
+fn foreground_rerenders_are_spread_out() {
+    let (data, glyphs) = font();
+    let mut handler = handler();
+    handler.set_foreground_rerender_batch(1);
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let mut old = vec![];
+    for &glyph in glyphs.iter() {
+        old.push(handler.get_glyph(face, glyph, &mut atlases).unwrap()
+                 .unwrap().1);
+    }
+    handler.set_render_params(face, sharper()).unwrap();
+    // One glyph per call, and the old ones until they're all done.
+    let (_, coords, _) = handler.get_glyph(face, glyphs[0], &mut atlases)
+        .unwrap().unwrap();
+    assert!(handler.is_rerendering(face));
+    assert_eq!(coords, old[0]);
+    assert_eq!(atlases.placed.len(), 3);
+    let (_, coords, _) = handler.get_glyph(face, glyphs[0], &mut atlases)
+        .unwrap().unwrap();
+    assert!(!handler.is_rerendering(face));
+    assert_eq!(atlases.placed[coords].width, 36);
+    assert_eq!(atlases.placed.len(), 4);
+}
+
+#[test]
+fn new_glyphs_wait_for_the_rest() {
+    let mut font = TestFont::new(1000);
+    let cached = [font.rect(0, 0, 500, 500), font.rect(0, 0, 500, 499)];
+    let new = font.rect(0, 0, 500, 498);
+    let mut handler = handler();
+    handler.set_foreground_rerender_batch(1);
+    let face = handler.add_face(Arc::new(font.build()), 0, 4.0, 32.0, 32.0)
+        .unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    for &glyph in cached.iter() {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap().unwrap();
+    }
+    handler.set_render_params(face, sharper()).unwrap();
+    // It would be drawn with the new parameters, next to old glyphs, so it
+    // isn't drawn at all yet.
+    assert!(handler.get_glyph(face, new, &mut atlases).unwrap().is_none());
+    assert!(handler.get_glyph_metrics(face, new).is_none());
+    // It joins the queue, one glyph per call, and comes out with the rest.
+    assert!(handler.get_glyph(face, new, &mut atlases).unwrap().is_none());
+    assert!(handler.is_rerendering(face));
+    let (_, coords, _) = handler.get_glyph(face, new, &mut atlases)
+        .unwrap().unwrap();
+    assert!(!handler.is_rerendering(face));
+    assert_eq!(atlases.placed[coords].width, 36);
+    // It was rendered only once.
+    assert_eq!(atlases.placed.len(), 5);
+}
+
+#[cfg(feature="bg-render")]
+#[test]
+fn old_glyphs_are_drawn_until_all_new_ones_are_ready() {
+    let (data, glyphs) = font();
+    let mut handler = psilo_text::TextHandler::new();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    let mut old = vec![];
+    for &glyph in glyphs.iter() {
+        loop {
+            if let Some((_, coords, _))
+                = handler.get_glyph(face, glyph, &mut atlases).unwrap() {
+                old.push(coords);
+                break
+            }
+            std::thread::yield_now();
+        }
+    }
+    handler.set_render_params(face, sharper()).unwrap();
+    while handler.is_rerendering(face) {
+        for (&glyph, &old) in glyphs.iter().zip(old.iter()) {
+            let (_, coords, _) = handler.get_glyph(face, glyph, &mut atlases)
+                .unwrap().unwrap();
+            if handler.is_rerendering(face) { assert_eq!(coords, old) }
+        }
+        std::thread::yield_now();
+    }
+    for &glyph in glyphs.iter() {
+        let (_, coords, _) = handler.get_glyph(face, glyph, &mut atlases)
+            .unwrap().unwrap();
+        assert_eq!(atlases.placed[coords].width, 36);
+    }
+    assert_eq!(atlases.placed.len(), 4);
+}
+
+#[cfg(feature="bg-render")]
+#[test]
+fn pending_glyphs_get_the_new_params() {
+    let (data, glyphs) = font();
+    let mut handler = psilo_text::TextHandler::new();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(128, 128);
+    assert!(handler.get_glyph(face, glyphs[0], &mut atlases).unwrap()
+            .is_none());
+    handler.set_render_params(face, sharper()).unwrap();
+    while handler.get_glyph(face, glyphs[0], &mut atlases).unwrap()
+        .is_none() {
+        std::thread::yield_now();
+    }
+    assert_eq!(atlases.placed.len(), 1);
+    assert_eq!(atlases.placed[0].width, 36);
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::{AtlasSpacing, Packing};
+use common::{MemoryAtlases, TestFont, handler};
+
+/// A font with a space, and ten 30×30-texel glyphs.
+fn font() -> (Arc<Vec<u8>>, u16, Vec<u16>) {
+    let mut font = TestFont::new(1000);
+    let space = font.glyph(&[], 250);
+    let glyphs = font.squares(10);
+    (Arc::new(font.build()), space, glyphs)
+}
+
+#[test]
+fn stats_count_renders_and_bytes() {
+    let (data, space, glyphs) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    for &glyph in glyphs[.. 3].iter().chain(&[space]) {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap();
+        // Asking again doesn't render again.
+        handler.get_glyph(face, glyph, &mut atlases).unwrap();
+    }
+    let stats = handler.stats();
+    assert_eq!(stats.rendering.foreground_renders, 4);
+    assert_eq!(stats.rendering.background_renders, 0);
+    let bytes: usize = atlases.placed.iter().map(|x| x.pixels.len()).sum();
+    assert_eq!(stats.rendering.rendered_bytes, bytes as u64);
+    assert_eq!(stats.atlases.len(), 1);
+    assert_eq!(stats.atlases[0].glyphs, 3);
+}
+
+#[cfg(feature="bg-render")]
+#[test]
+fn stats_count_background_renders() {
+    let (data, _, glyphs) = font();
+    let mut handler = psilo_text::TextHandler::new();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    assert!(handler.get_glyph(face, glyphs[0], &mut atlases).unwrap()
+            .is_none());
+    while handler.get_glyph(face, glyphs[0], &mut atlases).unwrap()
+        .is_none() {
+        std::thread::yield_now();
+    }
+    let stats = handler.stats();
+    assert_eq!(stats.rendering.foreground_renders, 0);
+    assert_eq!(stats.rendering.background_renders, 1);
+    assert!(stats.rendering.background_time > std::time::Duration::ZERO);
+    assert_eq!(stats.rendering.rendered_bytes,
+               atlases.placed[0].pixels.len() as u64);
+}
+
+#[test]
+fn cached_glyphs_match_their_placement() {
+    let (data, space, glyphs) = font();
+    let mut handler = handler();
+    let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+    let mut atlases = MemoryAtlases::new(64, 64);
+    for &glyph in glyphs.iter().chain(&[space]) {
+        handler.get_glyph(face, glyph, &mut atlases).unwrap();
+    }
+    let mut cached: Vec<_> = handler.cached_glyphs().collect();
+    // The space has nothing in an atlas.
+    assert_eq!(cached.len(), glyphs.len());
+    cached.sort_by_key(|x| x.glyph);
+    for (cached, &glyph) in cached.iter().zip(glyphs.iter()) {
+        assert_eq!((cached.face, cached.glyph, cached.group), (face, glyph, 0));
+        let placed = &atlases.placed[cached.coords];
+        assert_eq!(cached.atlas, placed.atlas);
+        assert_eq!((cached.info.atlas_rect.x, cached.info.atlas_rect.y),
+                   (placed.x, placed.y));
+    }
+}
+
+#[test]
+fn largest_free_rect_shrinks_as_atlases_fill() {
+    for packing in [Packing::Skyline, Packing::MaxRects, Packing::Shelf] {
+        let (data, _, glyphs) = font();
+        let mut handler = handler();
+        handler.set_packing(packing);
+        handler.set_atlas_spacing(AtlasSpacing { between_glyphs: 2,
+                                                 ..AtlasSpacing::default() });
+        let face = handler.add_face(data, 0, 4.0, 32.0, 32.0).unwrap();
+        let mut atlases = MemoryAtlases::new(64, 64);
+        handler.get_glyph(face, glyphs[0], &mut atlases).unwrap();
+        let free = handler.stats().atlases[0].largest_free.unwrap();
+        // Beside the first glyph, or above it, leaving space between.
+        assert!(free == (32, 64) || free == (64, 32),
+                "{:?}: {:?}", packing, free);
+        for &glyph in glyphs[1 .. 4].iter() {
+            handler.get_glyph(face, glyph, &mut atlases).unwrap();
+        }
+        // The atlas only fits four.
+        assert_eq!(atlases.atlases.len(), 1);
+        let free = handler.stats().atlases[0].largest_free.unwrap();
+        assert!(free.0 < 30 || free.1 < 30, "{:?}: {:?}", packing, free);
+    }
+}
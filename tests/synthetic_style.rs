@@ -0,0 +1,105 @@
+mod common;
+
+use std::sync::Arc;
+use psilo_text::SyntheticStyle;
+use common::{MemoryAtlases, Placed, TestFont, handler, inside_at, msdf_inside};
+
+/// Returns how much of the glyph is inside, in square ems, going by which
+/// texels are inside.
+fn coverage(placed: &Placed) -> f32 {
+    let (x_min, y_min, x_max, y_max) = placed.render_bounds;
+    let texel_area = (x_max - x_min) / placed.width as f32
+        * (y_max - y_min) / placed.height as f32;
+    let inside = (0 .. placed.height).flat_map(|y| {
+        (0 .. placed.width).map(move |x| (x, y))
+    }).filter(|&(x, y)| msdf_inside(placed, x, y)).count();
+    inside as f32 * texel_area
+}
+
+#[test]
+fn embolden_grows_the_outline() {
+    // A 200×700 unit rectangle, with a distance range of 4 texels.
+    let mut font = TestFont::new(1000);
+    let rect = font.rect(100, 0, 300, 700);
+    let data = Arc::new(font.build());
+    let mut handler = handler();
+    let plain = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    let (_, coords, _) = handler.get_glyph(plain, rect, &mut atlases)
+        .unwrap().unwrap();
+    let area = coverage(&atlases.placed[coords]);
+    assert!((area - 0.2 * 0.7).abs() < 0.01, "{}", area);
+    // 30 units is a little under two texels. Every side moves out by that
+    // much (and the corners get rounded off, which we can ignore at this
+    // size).
+    let bold = handler.add_face_variant(plain, SyntheticStyle {
+        embolden: 30.0, skew: 0.0,
+    }).unwrap();
+    let (_, coords, _) = handler.get_glyph(bold, rect, &mut atlases)
+        .unwrap().unwrap();
+    let placed = &atlases.placed[coords];
+    let expected = (0.2 + 0.06) * (0.7 + 0.06);
+    let area = coverage(placed);
+    assert!((area - expected).abs() < expected * 0.03,
+            "{} instead of {}", area, expected);
+    // The glyph doesn't fill its quad: there's still an outside.
+    assert!(!msdf_inside(placed, 0, 0));
+    assert!(!msdf_inside(placed, placed.width - 1, placed.height - 1));
+}
+
+#[test]
+fn skew_slants_the_outline() {
+    // A rectangle from 0.1 to 0.3 em across, leaning half an em to the
+    // right for every em up.
+    let mut font = TestFont::new(1000);
+    let rect = font.rect(100, 0, 300, 700);
+    let data = Arc::new(font.build());
+    let mut handler = handler();
+    let plain = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let oblique = handler.add_face_variant(plain, SyntheticStyle {
+        embolden: 0.0, skew: 0.5,
+    }).unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    let (_, coords, _) = handler.get_glyph(oblique, rect, &mut atlases)
+        .unwrap().unwrap();
+    let placed = &atlases.placed[coords];
+    // The quad leans with it...
+    let (x_min, _, x_max, _) = placed.render_bounds;
+    assert!(x_min < 0.1 && x_max > 0.65, "{} to {}", x_min, x_max);
+    // ...and so does the outline: near the baseline it's barely moved, but
+    // 0.6 em up it's 0.3 em to the right.
+    assert!(inside_at(placed, 0.25, 0.1));
+    assert!(!inside_at(placed, 0.2, 0.6));
+    assert!(inside_at(placed, 0.5, 0.6));
+    assert!(!inside_at(placed, 0.65, 0.6));
+}
+
+#[test]
+fn embolden_survives_higher_density_tiers() {
+    // At twice the density, 30 units is nearly four texels, more than half
+    // of the face's distance range.
+    let mut font = TestFont::new(1000);
+    let rect = font.rect(100, 0, 300, 700);
+    let data = Arc::new(font.build());
+    let mut handler = handler();
+    let plain = handler.add_face(data, 0, 4.0, 64.0, 64.0).unwrap();
+    let bold = handler.add_face_variant(plain, SyntheticStyle {
+        embolden: 30.0, skew: 0.0,
+    }).unwrap();
+    handler.set_density_tiers(bold, &[1.0, 2.0]).unwrap();
+    let mut atlases = MemoryAtlases::new(256, 256);
+    let (_, coords, _) = handler.get_glyph_for_size(bold, rect, 128.0,
+                                                    &mut atlases)
+        .unwrap().unwrap();
+    let placed = &atlases.placed[coords];
+    let expected = (0.2 + 0.06) * (0.7 + 0.06);
+    let area = coverage(placed);
+    assert!((area - expected).abs() < expected * 0.03,
+            "{} instead of {}", area, expected);
+    assert!(!msdf_inside(placed, 0, 0));
+    assert!(!msdf_inside(placed, placed.width - 1, placed.height - 1));
+    // The range was widened to make room for the shift.
+    let info = handler.get_glyph_info_for_size(bold, rect, 128.0).unwrap();
+    assert!(info.distance_range_texels > 4.0 + 2.0 * 3.8,
+            "{:?}", info);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use std::sync::Arc;
+use rustybuzz::Variation;
+use ttf_parser::Tag;
+use common::{MemoryAtlases, Placed, TestFont, handler, inside_at};
+
+fn wght(value: f32) -> Variation {
+    Variation { tag: Tag::from_bytes(b"wght"), value }
+}
+
+#[test]
+fn instances_of_one_font_render_differently() {
+    // A rectangle whose right edge moves from 0.5 em out to 0.7 em as the
+    // weight goes from 400 to 900.
+    let mut font = TestFont::new(1000);
+    font.axis(b"wght", 100.0, 400.0, 900.0);
+    let rect = font.rect(100, 0, 500, 700);
+    font.deltas(rect, &[(0, 0), (0, 0), (200, 0), (200, 0)]);
+    let data = Arc::new(font.build());
+    let mut handler = handler();
+    let regular = handler.add_face_with_variations(data.clone(), 0,
+                                                   &[wght(400.0)], 4.0,
+                                                   32.0, 32.0).unwrap();
+    let bold = handler.add_face_with_variations(data.clone(), 0,
+                                                &[wght(900.0)], 4.0,
+                                                32.0, 32.0).unwrap();
+    // Both instances use the same bytes.
+    for face in [regular, bold] {
+        assert_eq!(handler.get_face(face).unwrap().raw_face().data.as_ptr(),
+                   data.as_ptr());
+    }
+    let mut atlases = MemoryAtlases::new(256, 256);
+    let (_, regular, _) = handler.get_glyph(regular, rect, &mut atlases)
+        .unwrap().unwrap();
+    let (_, bold, _) = handler.get_glyph(bold, rect, &mut atlases)
+        .unwrap().unwrap();
+    assert_ne!(regular, bold);
+    let (regular, bold) = (&atlases.placed[regular], &atlases.placed[bold]);
+    // The quads are about 0.2 em apart in width (the padding is the
+    // same)...
+    let width = |placed: &Placed| {
+        placed.render_bounds.2 - placed.render_bounds.0
+    };
+    assert!((width(bold) - width(regular) - 0.2).abs() < 0.05,
+            "{} and {}", width(regular), width(bold));
+    assert!(bold.width > regular.width);
+    // ...and so are the outlines in them.
+    for (x, regular_inside) in [(0.2, true), (0.45, true), (0.6, false)] {
+        assert_eq!(inside_at(regular, x, 0.35), regular_inside, "{}", x);
+        assert!(inside_at(bold, x, 0.35), "{}", x);
+    }
+    assert!(!inside_at(bold, 0.75, 0.35));
+}